@@ -0,0 +1,49 @@
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+const packed_size: u32 = 16;
+
+struct LineGraphSettings {
+    values: array<vec4<f32>, packed_size>,
+    count: u32,
+}
+
+@group(1) @binding(0) var<uniform> settings: LineGraphSettings;
+
+fn get_value(index: u32) -> f32 {
+    return settings.values[index / 4][index % 4];
+}
+
+fn get_point(index: u32, size: vec2<f32>) -> vec2<f32> {
+    let x = f32(index) / f32(max(settings.count, 2u) - 1u);
+    return vec2<f32>(x * size.x, (1. - get_value(index)) * size.y);
+}
+
+fn segment_distance(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let ab = b - a;
+    let t = clamp(dot(p - a, ab) / max(dot(ab, ab), 0.0001), 0., 1.);
+    return length(p - (a + ab * t));
+}
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let p = in.uv * in.size;
+    // Baseline along the bottom so empty graphs still read as graphs.
+    if p.y > in.size.y - 1. {
+        return vec4<f32>(1., 1., 1., 0.5);
+    }
+    if settings.count < 2u {
+        return vec4<f32>(0.);
+    }
+    let step = in.size.x / f32(settings.count - 1u);
+    let center = u32(clamp(p.x / step, 0., f32(settings.count - 2u)));
+    var dist = 1000.;
+    let first = select(center - 1u, 0u, center == 0u);
+    let last = min(center + 1u, settings.count - 2u);
+    for (var i = first; i <= last; i++) {
+        dist = min(dist, segment_distance(p, get_point(i, in.size), get_point(i + 1u, in.size)));
+    }
+    if dist > 1. {
+        return vec4<f32>(0.);
+    }
+    return vec4<f32>(1.);
+}
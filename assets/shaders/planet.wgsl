@@ -1,24 +1,85 @@
 #import bevy_sprite::{mesh2d_vertex_output::VertexOutput, mesh2d_view_bindings::globals}
 const pi = radians(180.0);
 const e = 2.71828;
-const array_size: u32 = 8;
+const feature_water: u32 = 0;
+const feature_biome: u32 = 1;
+const feature_crater: u32 = 2;
+// Matches the Biome enum.
+const biome_swamp: u32 = 1;
+const biome_highlands: u32 = 3;
+
+const elevation_samples: u32 = 360;
 
 struct PlanetSettings {
-    hole_array: array<vec4<f32>, array_size>
+    radius: f32,
+    extent: f32,
+    feature_count: u32,
+    sun_degrees: f32,
+    night_brightness: f32,
+}
+
+struct PlanetFeature {
+    start_degrees: f32,
+    end_degrees: f32,
+    kind: u32,
+    data: f32,
 }
 
 @group(2) @binding(0) var<uniform> properties: PlanetSettings;
+@group(2) @binding(1) var<storage, read> features: array<PlanetFeature>;
+// World units above the radius, one sample per degree, see PlanetElevation.
+@group(2) @binding(2) var<storage, read> elevation: array<f32>;
 
 fn pos_sin(x: f32) -> f32 {
     return (sin(x) + 1.) / 2.0;
 }
 
-fn generate_elevation(angle: f32, frequency: f32, amplitude: f32) -> f32 {
-    return pos_sin(angle * frequency) * amplitude;
+// Height of the ground at this angle, where 1 is the planet's radius. Mirrors PlanetElevation::height_at.
+fn ground_height(pos_deg: f32) -> f32 {
+    let position = pos_deg / 360. * f32(elevation_samples);
+    let index = u32(floor(position)) % elevation_samples;
+    let next = (index + 1u) % elevation_samples;
+    let height = mix(elevation[index], elevation[next], fract(position));
+    return 1. + height / properties.radius;
+}
+
+// Degrees from the start of the feature, negative when outside of it. Works across 0°.
+fn feature_offset(feature: PlanetFeature, pos_deg: f32) -> f32 {
+    let size = feature.end_degrees - feature.start_degrees;
+    let offset = (pos_deg - feature.start_degrees + 720.) % 360.;
+    if (offset > size) {
+        return -1.;
+    }
+    return offset;
 }
 
-fn get_hole(index: u32) -> vec2<f32> {
-    return properties.hole_array[index].xy;
+// A deck level with the ground, held up by evenly spaced pillars.
+fn is_bridge(offset: f32, height: f32, depth: f32) -> bool {
+    if (height > 0.975) {
+        return true;
+    }
+    let pillar = fract(offset / 7.5);
+    return height > 1. - depth && (pillar < 0.1 || pillar > 0.9);
+}
+
+fn speckle(pos_deg: f32, height: f32) -> f32 {
+    return fract(sin(floor(pos_deg * 4.) * 12.9898 + floor(height * 200.) * 78.233) * 43758.5453);
+}
+
+// Rocky ground for the highlands and speckled ground for the swamps and craters.
+fn handle_ground(biome: u32, scorched: bool, pos_deg: f32, height: f32, ground: f32) -> vec4<f32> {
+    if (biome == biome_highlands) {
+        let jag = pos_sin(pos_deg * 9.) * 0.012 + pos_sin(pos_deg * 23.) * 0.006;
+        if (height > ground - jag) {
+            return vec4<f32>(0.);
+        }
+    }
+    let speckled = (biome == biome_swamp && speckle(pos_deg, height) > 0.85)
+        || (scorched && speckle(pos_deg, height) > 0.6);
+    if (height > ground - 0.04 && speckled) {
+        return vec4<f32>(0., 0., 0., 1.);
+    }
+    return vec4<f32>(vec3<f32>(get_light(pos_deg)), 1.);
 }
 
 fn generate_wave(angle: f32, frequency: f32, amplitude: f32, time: f32, time_mult: f32) -> f32 {
@@ -41,6 +102,14 @@ fn handle_water(height: f32, angle: f32, time: f32) -> vec4<f32> {
     return vec4<f32>(0., 0.0, 0.0, 0.0);
 }
 
+// Mirrors DayCycle::daylight on the CPU side.
+fn get_light(pos_deg: f32) -> f32 {
+    let sun_dist = abs(pos_deg - properties.sun_degrees) % 360.;
+    let angle = radians(min(sun_dist, 360. - sun_dist));
+    let daylight = clamp((cos(angle) + 0.2) / 0.4, 0., 1.);
+    return mix(properties.night_brightness, 1., daylight);
+}
+
 fn normalized_sigmoid(x: f32) -> f32 {
     let expo = exp((-x + 0.5) * 10.);
     return 1. / (1 + expo);
@@ -69,33 +138,42 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
     let centered_uv = in.uv * 2. - vec2<f32>(1., 1.);
     let angle = atan2(centered_uv.y, centered_uv.x);
     let time = globals.time / 3.; 
-    let height = (length(centered_uv));
+    let height = length(centered_uv) * properties.extent / properties.radius;
     var pos_deg = ((angle * 180) / pi) + 90;
     if pos_deg < 0 {
         pos_deg = pos_deg + 360.;
     }
-    if (height >= 1.0) {
+    let ground = ground_height(pos_deg);
+    if (height >= ground) {
         return vec4<f32>(0.0);
     }
-    
-    for (var i: u32 = 0u; i < array_size; i = i + 1u) {
-        let hole: vec2<f32> = get_hole(i);
-        if (pos_deg > hole.x && pos_deg < hole.y) {
-            let hole_size = distance(hole.x, hole.y);
-            let depth_mult = hole_size;
-            let dist_x = distance(pos_deg, hole.x);
-            let dist_y = distance(pos_deg, hole.y);
-            let shore_dist = min(dist_x, dist_y);
-            let r = (shore_dist / (hole_size / 2));
-            //let log = (log(r) + 1.) / 10.;
-            let log = 0.;
+
+    var biome: u32 = 2u;
+    var scorched = false;
+    for (var i: u32 = 0u; i < properties.feature_count; i = i + 1u) {
+        let feature = features[i];
+        let offset = feature_offset(feature, pos_deg);
+        if (offset < 0.) {
+            continue;
+        }
+        let size = feature.end_degrees - feature.start_degrees;
+        if (feature.kind == feature_biome) {
+            biome = u32(feature.data);
+        } else if (feature.kind == feature_crater) {
+            scorched = true;
+        } else if (feature.kind == feature_water) {
+            let shore_dist = min(offset, size - offset);
             let random_variation = sig(shore_dist * 0.6, 2.) * noise(pos_deg, 0.02);
             let absolute_depth = sig(shore_dist / 5., 2.) + random_variation;
             let depth = absolute_depth * 0.1;
+            if (feature.data > 0.5 && is_bridge(offset, height, depth)) {
+                return vec4<f32>(vec3<f32>(get_light(pos_deg)), 1.);
+            }
             if (height > 1. - depth) {
-                return handle_water(height, angle, time);
+                let water = handle_water(height, angle, time);
+                return vec4<f32>(water.rgb * get_light(pos_deg), water.a);
             }
         }
     }
-    return vec4<f32>(1.);
+    return handle_ground(biome, scorched, pos_deg, height, ground);
 }
\ No newline at end of file
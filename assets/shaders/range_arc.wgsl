@@ -0,0 +1,39 @@
+#import bevy_sprite::mesh2d_vertex_output::VertexOutput
+const pi = radians(180.0);
+
+struct RangeArcSettings {
+    radius: f32,
+    extent: f32,
+    center_degrees: f32,
+    half_span_degrees: f32,
+}
+
+@group(2) @binding(0) var<uniform> properties: RangeArcSettings;
+
+fn angle_distance(a: f32, b: f32) -> f32 {
+    let diff = abs(a - b) % 360.;
+    return min(diff, 360. - diff);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let centered_uv = in.uv * 2. - vec2<f32>(1., 1.);
+    let angle = atan2(centered_uv.y, centered_uv.x);
+    var angle_deg = ((angle * 180) / pi) + 90;
+    if angle_deg < 0 {
+        angle_deg = angle_deg + 360.;
+    }
+    let dist = length(centered_uv) * properties.extent;
+    let from_center = angle_distance(angle_deg, properties.center_degrees);
+    if from_center > properties.half_span_degrees {
+        return vec4<f32>(0.0);
+    }
+    // Thin band hugging the surface, with taller ticks marking both ends of the range.
+    let tick_width = degrees(1.5 / properties.radius);
+    let is_tick = properties.half_span_degrees - from_center < tick_width && properties.half_span_degrees < 180.;
+    let height = select(2., 6., is_tick);
+    if dist < properties.radius || dist > properties.radius + height {
+        return vec4<f32>(0.0);
+    }
+    return vec4<f32>(1., 1., 1., 0.8);
+}
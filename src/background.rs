@@ -43,7 +43,7 @@ fn handle_background(
     mut background_query: Query<&mut Transform, With<Background>>,
     window: Query<&Window>
 ) {
-    let window = window.single();
+    let Ok(window) = window.get_single() else { return; };
     for mut background_transform in background_query.iter_mut() {
         background_transform.scale = Vec3{ x: window.width() / 100., y: window.width() / 100., z: 1.};
     }
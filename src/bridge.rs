@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+
+use crate::{looping_float::LoopingFloat, pathfinding::SurfaceGraphs, planet_sticker::{Contains, PlanetSticker}, structure::spawn_structure};
+
+// Longest stretch of water, in world units along the surface, that a bridge can span.
+pub const MAX_BRIDGE_LENGTH: f32 = 96.;
+// World units past the water's edge that builders stand on the shore.
+const SHORE_MARGIN: f32 = 4.;
+
+#[derive(Component)]
+pub struct Bridge;
+
+pub fn spawn_bridge(commands: &mut Commands, texture: Handle<Image>, planet: Entity, position_degrees: f32) -> Entity {
+    let bridge = spawn_structure(commands, texture, planet, position_degrees, "Bridge");
+    commands.entity(bridge).insert(Bridge);
+    return bridge;
+}
+
+pub fn is_bridged<'a>(water: &PlanetSticker, mut bridges: impl Iterator<Item = &'a PlanetSticker>) -> bool {
+    return bridges.any(|bridge| bridge.planet == water.planet && water.contains(bridge.position_degrees.to_f32()));
+}
+
+// Nobody can stand on unbridged water, so a site over it is worked from whichever shore is cheaper to reach.
+// None when the site isn't over water, or when neither shore can be reached.
+pub fn reachable_shore<'a>(
+    sticker: &PlanetSticker,
+    site: &PlanetSticker,
+    mut waters: impl Iterator<Item = &'a PlanetSticker>,
+    graphs: &SurfaceGraphs,
+) -> Option<LoopingFloat<360>> {
+    let water = waters.find(|water| water.planet == site.planet && water.contains(site.position_degrees.to_f32()))?;
+    let reach = water.size_degrees.unwrap_or(0.) / 2. + graphs.degrees_for(sticker, SHORE_MARGIN);
+    return [water.position_degrees - reach, water.position_degrees + reach].into_iter()
+        .filter_map(|shore| graphs.find_path(sticker, shore).map(|path| (shore, path.cost)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(shore, _)| shore);
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+
+use crate::{
+    occupable::{Automator, HarvestPreference, ResourceType, SelectedOccupable, SelectionChanged},
+    planet_placing::{Building, GetBuildingInfo},
+    upgrades::{level_name, UpgradeRequest},
+};
+
+#[derive(Component)]
+struct BuildingPanel;
+
+#[derive(Component, Clone, Copy)]
+enum PanelAction {
+    ToggleResource(ResourceType),
+    CycleReserve,
+    TogglePreference,
+    Upgrade,
+}
+
+#[derive(Component)]
+struct PanelTitle;
+
+#[derive(Component)]
+struct PanelButtonLabel;
+
+const MAX_RESERVE: u32 = 5;
+
+pub struct BuildingPanelPlugin;
+
+impl Plugin for BuildingPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_panel)
+            .add_systems(Update, (rebuild_panel, handle_panel_buttons, update_panel_labels).chain());
+    }
+}
+
+fn spawn_panel(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(5.),
+            bottom: Val::Px(5.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.)),
+            row_gap: Val::Px(4.),
+            border: UiRect::all(Val::Px(2.)),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK),
+        BorderColor(Color::WHITE),
+        Visibility::Hidden,
+        BuildingPanel,
+        Name::new("BuildingPanel"),
+    ));
+}
+
+fn rebuild_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut ev_selection: EventReader<SelectionChanged>,
+    mut panel_query: Query<(Entity, &mut Visibility), With<BuildingPanel>>,
+    buildings_query: Query<(&Building, Option<&Automator>)>,
+) {
+    let Some(selection) = ev_selection.read().last() else { return; };
+    let Ok((panel, mut visibility)) = panel_query.get_single_mut() else { return; };
+    commands.entity(panel).despawn_descendants();
+    let Some((building, automator)) = selection.current.and_then(|selected| buildings_query.get(selected).ok()) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Visible;
+    let font = TextFont {
+        font: asset_server.load("fonts/pixel.ttf"),
+        font_size: 16.0,
+        ..default()
+    };
+    let mut actions: Vec<PanelAction> = vec![];
+    if let Some(automator) = automator {
+        actions.extend(automator.exploited_resources.iter().map(|resource_type| PanelAction::ToggleResource(*resource_type)));
+        actions.push(PanelAction::CycleReserve);
+        actions.push(PanelAction::TogglePreference);
+    }
+    if building.building_type.get_upgrade_info(2).is_some() {
+        actions.push(PanelAction::Upgrade);
+    }
+    commands.entity(panel).with_children(|parent| {
+        parent.spawn((Text::new(""), font.clone(), PanelTitle));
+        for action in actions {
+            parent.spawn((
+                Button,
+                Node {
+                    padding: UiRect::axes(Val::Px(6.), Val::Px(2.)),
+                    border: UiRect::all(Val::Px(1.)),
+                    ..default()
+                },
+                BorderColor(Color::WHITE),
+                BackgroundColor(Color::BLACK),
+                action,
+            )).with_children(|button| {
+                button.spawn((Text::new(""), font.clone(), PanelButtonLabel));
+            });
+        }
+    });
+}
+
+fn handle_panel_buttons(
+    buttons_query: Query<(&Interaction, &PanelAction), Changed<Interaction>>,
+    selected_occupable: Res<SelectedOccupable>,
+    mut automators_query: Query<&mut Automator>,
+    mut ev_upgrade: EventWriter<UpgradeRequest>,
+) {
+    let Some(selected) = selected_occupable.occupable else { return; };
+    for (interaction, action) in buttons_query.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        if let PanelAction::Upgrade = action {
+            ev_upgrade.send(UpgradeRequest { building: selected });
+            continue;
+        }
+        let Ok(mut automator) = automators_query.get_mut(selected) else { continue; };
+        match *action {
+            PanelAction::ToggleResource(resource_type) => {
+                if let Some(index) = automator.harvested_resources.iter().position(|harvested| *harvested == resource_type) {
+                    automator.harvested_resources.remove(index);
+                } else {
+                    automator.harvested_resources.push(resource_type);
+                }
+            }
+            PanelAction::CycleReserve => automator.reserve = (automator.reserve + 1) % (MAX_RESERVE + 1),
+            PanelAction::TogglePreference => {
+                automator.preference = match automator.preference {
+                    HarvestPreference::Nearest => HarvestPreference::MostAbundant,
+                    HarvestPreference::MostAbundant => HarvestPreference::Nearest,
+                }
+            }
+            PanelAction::Upgrade => {}
+        }
+    }
+}
+
+fn format_cost(cost: &[(ResourceType, i32)]) -> String {
+    return cost.iter().map(|(resource_type, amount)| format!("{} {:?}", amount, resource_type)).collect::<Vec<String>>().join(", ");
+}
+
+fn update_panel_labels(
+    buttons_query: Query<(&PanelAction, &Children)>,
+    mut labels_query: Query<&mut Text, (With<PanelButtonLabel>, Without<PanelTitle>)>,
+    mut title_query: Query<&mut Text, With<PanelTitle>>,
+    selected_occupable: Res<SelectedOccupable>,
+    buildings_query: Query<(&Building, Option<&Automator>)>,
+) {
+    let Some((building, automator)) = selected_occupable.occupable.and_then(|selected| buildings_query.get(selected).ok()) else { return; };
+    let info = building.building_type.get_building_info();
+    for mut title in title_query.iter_mut() {
+        title.0 = format!("{} {}", info.name, level_name(building.level));
+    }
+    for (action, children) in buttons_query.iter() {
+        let label = match (*action, automator) {
+            (PanelAction::ToggleResource(resource_type), Some(automator)) => {
+                let state = if automator.harvested_resources.contains(&resource_type) { "on" } else { "off" };
+                format!("{:?}: {}", resource_type, state)
+            }
+            (PanelAction::CycleReserve, Some(automator)) => format!("Leave: {}", automator.reserve),
+            (PanelAction::TogglePreference, Some(automator)) => format!("Prefer: {:?}", automator.preference),
+            (PanelAction::Upgrade, _) => match building.building_type.get_upgrade_info(building.level + 1) {
+                Some(upgrade) => format!("Upgrade: {}", format_cost(upgrade.cost)),
+                None => "Max level".to_owned(),
+            },
+            _ => continue,
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = labels_query.get_mut(*child) {
+                text.0 = label.clone();
+            }
+        }
+    }
+}
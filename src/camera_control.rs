@@ -0,0 +1,207 @@
+use bevy::{input::mouse::{MouseMotion, MouseWheel}, prelude::*};
+use bevy_pancam::PanCam;
+
+use crate::{
+    group_selection::GroupSelection,
+    occupable::SelectedOccupable,
+    planet::{Planet, Planets},
+    planet_sticker::{surface_point, PlanetSticker},
+    planet_villager::{PlanetVillager, VillagerWorking},
+};
+
+// How quickly the camera closes in on its target, higher is snappier.
+const SMOOTHING: f32 = 6.;
+// Planet diameters that fit across the shorter side of the window when focusing one.
+const FOCUS_MARGIN: f32 = 1.3;
+// Pixels the mouse has to move with a grab button held before it counts as panning rather than a click.
+const DRAG_THRESHOLD: f32 = 4.;
+const PAN_KEYS: [KeyCode; 8] = [
+    KeyCode::KeyW,
+    KeyCode::KeyA,
+    KeyCode::KeyS,
+    KeyCode::KeyD,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowRight,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CameraMode {
+    Free,
+    Planet(Entity),
+    Follow(Entity),
+    Surface { planet: Entity, position_degrees: f32 },
+}
+
+#[derive(Resource)]
+pub struct CameraController {
+    pub mode: CameraMode,
+    // Turns the view so the followed spot's ground is at the bottom of the screen.
+    pub rotate_up: bool,
+}
+
+impl Default for CameraController {
+    fn default() -> CameraController {
+        CameraController {
+            mode: CameraMode::Free,
+            rotate_up: false,
+        }
+    }
+}
+
+pub struct CameraControlPlugin;
+
+impl Plugin for CameraControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraController::default())
+            .add_systems(Update, (handle_camera_hotkeys, release_on_manual_control, move_camera).chain());
+    }
+}
+
+fn handle_camera_hotkeys(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut controller: ResMut<CameraController>,
+    planets: Res<Planets>,
+    selected_occupable: Res<SelectedOccupable>,
+    group: Res<GroupSelection>,
+    villagers_query: Query<(), With<PlanetVillager>>,
+    workers_query: Query<(Entity, &VillagerWorking)>,
+) {
+    let step: i32 = if keys.just_pressed(KeyCode::Period) {
+        1
+    } else if keys.just_pressed(KeyCode::Comma) {
+        -1
+    } else {
+        0
+    };
+    if step != 0 && !planets.all.is_empty() {
+        let current = match controller.mode {
+            CameraMode::Planet(planet) => planets.all.iter().position(|other| *other == planet),
+            _ => None,
+        };
+        let count = planets.all.len() as i32;
+        let next = match current {
+            Some(index) => (index as i32 + step).rem_euclid(count),
+            None => if step > 0 { 0 } else { count - 1 },
+        };
+        controller.mode = CameraMode::Planet(planets.all[next as usize]);
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        let shift = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+        // F follows the selected villagers, shift+F the workers of the selected building.
+        let candidates: Vec<Entity> = if shift {
+            let mut workers: Vec<Entity> = workers_query.iter()
+                .filter(|(_, worker)| Some(worker.current_occupable) == selected_occupable.occupable)
+                .map(|(villager, _)| villager)
+                .collect();
+            workers.sort();
+            workers
+        } else {
+            group.entities.iter().copied().filter(|entity| villagers_query.contains(*entity)).collect()
+        };
+        // Repeated presses go through them one after the other.
+        let current = match controller.mode {
+            CameraMode::Follow(followed) => candidates.iter().position(|candidate| *candidate == followed),
+            _ => None,
+        };
+        let next = current.map_or(0, |index| index + 1);
+        if let Some(candidate) = candidates.get(next % candidates.len().max(1)) {
+            controller.mode = CameraMode::Follow(*candidate);
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyU) {
+        match (controller.mode, selected_occupable.occupable) {
+            (CameraMode::Follow(_) | CameraMode::Surface { .. }, _) => controller.rotate_up = !controller.rotate_up,
+            (_, Some(selected)) => {
+                controller.mode = CameraMode::Follow(selected);
+                controller.rotate_up = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn release_on_manual_control(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut ev_wheel: EventReader<MouseWheel>,
+    mut ev_motion: EventReader<MouseMotion>,
+    mut controller: ResMut<CameraController>,
+    camera_query: Query<&PanCam>,
+    mut dragged: Local<f32>,
+) {
+    let Ok(pan_cam) = camera_query.get_single() else { return; };
+    let zoomed = ev_wheel.read().count() > 0;
+    let moved: f32 = ev_motion.read().map(|motion| motion.delta.length()).sum();
+    // Plain clicks select things, only an actual drag takes the camera back.
+    if mouse_buttons.any_pressed(pan_cam.grab_buttons.iter().copied()) {
+        *dragged += moved;
+    } else {
+        *dragged = 0.;
+    }
+    let panned = keys.any_pressed(PAN_KEYS) || *dragged > DRAG_THRESHOLD;
+    // Zooming is fine while following something, but would fight the fitted zoom of a focused planet.
+    if panned || (zoomed && matches!(controller.mode, CameraMode::Planet(_))) {
+        controller.mode = CameraMode::Free;
+    }
+}
+
+fn move_camera(
+    mut controller: ResMut<CameraController>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<PanCam>>,
+    planets_query: Query<(&Planet, &Transform), Without<PanCam>>,
+    stickers_query: Query<&PlanetSticker>,
+    window_query: Query<&Window>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut projection)) = camera_query.get_single_mut() else { return; };
+    let mut target_translation: Option<Vec2> = None;
+    let mut target_rotation = Quat::IDENTITY;
+    let mut target_scale: Option<f32> = None;
+    let surface = match controller.mode {
+        CameraMode::Free => None,
+        CameraMode::Planet(planet) => {
+            let Ok((planet_info, planet_transform)) = planets_query.get(planet) else {
+                controller.mode = CameraMode::Free;
+                return;
+            };
+            target_translation = Some(planet_transform.translation.xy());
+            if let Ok(window) = window_query.get_single() {
+                let shortest_side = window.width().min(window.height()).max(1.);
+                target_scale = Some(planet_info.radius * 2. * FOCUS_MARGIN / shortest_side);
+            }
+            None
+        }
+        CameraMode::Follow(entity) => {
+            let Some((planet, position_degrees)) = stickers_query.get(entity).ok()
+                .and_then(|sticker| sticker.planet.map(|planet| (planet, sticker.position_degrees.to_f32()))) else {
+                controller.mode = CameraMode::Free;
+                return;
+            };
+            Some((planet, position_degrees))
+        }
+        CameraMode::Surface { planet, position_degrees } => Some((planet, position_degrees)),
+    };
+    if let Some((planet, position_degrees)) = surface {
+        let Ok((planet_info, planet_transform)) = planets_query.get(planet) else {
+            controller.mode = CameraMode::Free;
+            return;
+        };
+        let (position, up) = surface_point(planet_transform, planet_info.radius, position_degrees);
+        target_translation = Some(position.xy());
+        if controller.rotate_up {
+            // Turning the view along with the spot levels the ground under it.
+            target_rotation = up;
+        }
+    }
+    let blend = 1. - (-SMOOTHING * time.delta_secs()).exp();
+    if let Some(target) = target_translation {
+        let translation = transform.translation.xy().lerp(target, blend);
+        transform.translation = translation.extend(transform.translation.z);
+    }
+    transform.rotation = transform.rotation.slerp(target_rotation, blend);
+    if let Some(target) = target_scale {
+        projection.scale += (target - projection.scale) * blend;
+    }
+}
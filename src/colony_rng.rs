@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+// Every gameplay roll goes through this so a seeded run plays out the same way each time.
+#[derive(Resource)]
+pub struct ColonyRng {
+    pub rng: StdRng,
+}
+
+impl ColonyRng {
+    pub fn new(seed: Option<u64>) -> ColonyRng {
+        ColonyRng {
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+        }
+    }
+}
+
+// Systems that roll from the ColonyRng, run one after the other so a seed always gives the same rolls in the same order.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ColonyRngSet {
+    Resources,
+    Villagers,
+    Spawn,
+    Hazards,
+}
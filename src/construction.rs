@@ -0,0 +1,224 @@
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{
+    bridge::reachable_shore,
+    occupable::{spawn_building, spawn_occupable, OccupableParameters, OccupableType, ResourceType},
+    notifications::{ColonyEvent, ColonyEventKind},
+    pathfinding::{CachedPath, SurfaceGraphs},
+    planet::PlanetWater,
+    planet_placing::{BuildingType, GetBuildingInfo},
+    planet_sticker::PlanetSticker,
+    planet_villager::{find_nearest_storage, walk_towards, PlanetVillagerAnimationState, VillagerFleeing, VillagerInventory, VillagerSleeping, VillagerWorking, WalkResult, WORK_DISTANCE, WORKER_SPEED},
+    progress_indicator::{spawn_progress_indicator, ProgressIndicator},
+    resources::Resources,
+    spritesheet_animator::SpritesheetAnimator,
+    storage::{deposit, Storage},
+};
+
+#[derive(Component)]
+pub struct ConstructionSite {
+    pub building_type: BuildingType,
+    pub delivered: Vec<(ResourceType, i32)>,
+    // What is still set aside in `Resources` for this site.
+    pub reserved: Vec<(ResourceType, i32)>,
+    pub progress: f32,
+}
+
+impl ConstructionSite {
+    pub fn new(building_type: BuildingType) -> ConstructionSite {
+        let cost = building_type.get_building_info().cost;
+        ConstructionSite {
+            building_type,
+            delivered: cost.iter().map(|(resource_type, _)| (*resource_type, 0)).collect(),
+            reserved: cost.to_vec(),
+            progress: 0.,
+        }
+    }
+
+    // The first material that still has to be brought over, with how much is left to deliver.
+    pub fn missing_material(&self) -> Option<(ResourceType, i32)> {
+        let cost = self.building_type.get_building_info().cost;
+        for ((resource_type, required), (_, delivered)) in cost.iter().zip(self.delivered.iter()) {
+            if delivered < required {
+                return Some((*resource_type, required - delivered));
+            }
+        }
+        return None;
+    }
+
+    pub fn deliver(&mut self, resource_type: ResourceType, amount: i32) {
+        for (delivered_type, delivered) in self.delivered.iter_mut() {
+            if *delivered_type == resource_type {
+                *delivered += amount;
+            }
+        }
+    }
+
+    pub fn reserved_amount(&self, resource_type: ResourceType) -> i32 {
+        return self.reserved.iter().filter(|(reserved_type, _)| *reserved_type == resource_type).map(|(_, amount)| amount).sum();
+    }
+
+    // Hands over the reservation once a builder has picked the materials up from storage.
+    pub fn collect(&mut self, resource_type: ResourceType, amount: i32, resources: &mut Resources) {
+        for (reserved_type, reserved) in self.reserved.iter_mut() {
+            if *reserved_type != resource_type { continue; }
+            let released = amount.min(*reserved);
+            *reserved -= released;
+            resources.release(resource_type, released);
+        }
+    }
+
+    // Materials count for the first half of the bar, build time for the second.
+    pub fn completion(&self) -> f32 {
+        let cost = self.building_type.get_building_info().cost;
+        let required: i32 = cost.iter().map(|(_, amount)| amount).sum();
+        let delivered: i32 = self.delivered.iter().map(|(_, amount)| amount).sum();
+        let materials = if required == 0 { 1. } else { delivered as f32 / required as f32 };
+        let build = self.progress / self.building_type.get_building_info().build_time;
+        return (materials + build.clamp(0., 1.)) / 2.;
+    }
+}
+
+pub struct ConstructionPlugin;
+
+impl Plugin for ConstructionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_builders, update_construction_indicators, finish_construction_sites).chain());
+    }
+}
+
+fn handle_builders(
+    mut villager_query: Query<(
+        &mut VillagerWorking,
+        &mut PlanetSticker,
+        &mut Sprite,
+        &mut SpritesheetAnimator,
+        &mut VillagerInventory,
+        &mut CachedPath,
+    ), (Without<VillagerSleeping>, Without<VillagerFleeing>)>,
+    mut sites_query: Query<(&mut ConstructionSite, &PlanetSticker), Without<VillagerWorking>>,
+    storages_query: Query<&PlanetSticker, (With<Storage>, Without<VillagerInventory>)>,
+    waters_query: Query<&PlanetSticker, (With<PlanetWater>, Without<VillagerWorking>)>,
+    graphs: Res<SurfaceGraphs>,
+    mut resources: ResMut<Resources>,
+    time: Res<Time>,
+) {
+    for (mut worker, sticker, sprite, mut animator, mut inventory, mut cached_path) in villager_query.iter_mut() {
+        let Ok((mut site, site_sticker)) = sites_query.get_mut(worker.current_work) else { continue; };
+        animator.current_animation_index = PlanetVillagerAnimationState::Idle as u32;
+        let site_position = reachable_shore(&sticker, site_sticker, waters_query.iter(), &graphs).unwrap_or(
+            site_sticker.position_degrees + sticker.position_degrees.direction(site_sticker.position_degrees.to_f32()) as f32 * -graphs.degrees_for(&sticker, WORK_DISTANCE)
+        );
+        let Some((missing, needed)) = site.missing_material() else {
+            let walk = walk_towards(&mut animator, sticker, sprite, &graphs, &mut cached_path, time.delta_secs(), site_position, WORKER_SPEED);
+            worker.track_walk(&walk, time.delta_secs());
+            if walk == WalkResult::Arrived {
+                animator.current_animation_index = PlanetVillagerAnimationState::Cut as u32;
+                site.progress += time.delta_secs();
+            }
+            continue;
+        };
+        if inventory.carried == Some(missing) {
+            let walk = walk_towards(&mut animator, sticker, sprite, &graphs, &mut cached_path, time.delta_secs(), site_position, WORKER_SPEED);
+            worker.track_walk(&walk, time.delta_secs());
+            if walk == WalkResult::Arrived {
+                let delivered = (inventory.amount as i32).min(needed);
+                site.deliver(missing, delivered);
+                inventory.amount -= delivered as u32;
+                if inventory.amount == 0 {
+                    inventory.carried = None;
+                }
+            }
+            continue;
+        }
+        let Some(storage_position) = find_nearest_storage(&sticker, &storages_query, &graphs) else {
+            worker.track_walk(&WalkResult::NoPath, time.delta_secs());
+            continue;
+        };
+        let walk = walk_towards(&mut animator, sticker, sprite, &graphs, &mut cached_path, time.delta_secs(), storage_position, WORKER_SPEED);
+        worker.track_walk(&walk, time.delta_secs());
+        if walk == WalkResult::Arrived {
+            // Drop off whatever else is being carried before picking up the material.
+            deposit(&mut inventory, &mut resources);
+            // Only what was set aside for this site or isn't promised to any other.
+            let claimable = site.reserved_amount(missing) + resources.available(missing).max(0);
+            let taken = needed.min(inventory.capacity as i32).min(claimable).min(resources.amount(missing));
+            if taken > 0 {
+                resources.add(missing, -taken);
+                site.collect(missing, taken, &mut resources);
+                inventory.carried = Some(missing);
+                inventory.amount = taken as u32;
+            }
+        }
+    }
+}
+
+fn update_construction_indicators(
+    mut indicators_query: Query<(&Parent, &mut ProgressIndicator)>,
+    sites_query: Query<&ConstructionSite>,
+) {
+    for (parent, mut indicator) in indicators_query.iter_mut() {
+        let Ok(site) = sites_query.get(parent.get()) else { continue; };
+        indicator.progress = site.completion();
+    }
+}
+
+fn finish_construction_sites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    sites_query: Query<(Entity, &ConstructionSite, &PlanetSticker)>,
+    mut ev_colony: EventWriter<ColonyEvent>,
+) {
+    for (site_entity, site, site_sticker) in sites_query.iter() {
+        if site.missing_material().is_some() { continue; }
+        if site.progress < site.building_type.get_building_info().build_time { continue; }
+        let Some(planet) = site_sticker.planet else { continue; };
+        commands.entity(site_entity).despawn_recursive();
+        spawn_building(&mut commands, &asset_server, planet, site_sticker.position_degrees.to_f32(), site.building_type);
+        ev_colony.send(ColonyEvent {
+            kind: ColonyEventKind::BuildingFinished(site.building_type),
+            planet,
+            position_degrees: site_sticker.position_degrees.to_f32(),
+        });
+    }
+}
+
+pub fn spawn_construction_site(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    planet: Entity,
+    position_degrees: f32,
+    building_type: BuildingType,
+    resources: &mut Resources,
+) -> Entity {
+    let occupable_parameters = OccupableParameters::new(
+        asset_server.load("buildings/scaffold.png"),
+        planet,
+        position_degrees,
+        OccupableType::Construction,
+        3,
+        16.,
+        Anchor::BottomCenter,
+    );
+    let site = spawn_occupable(commands, occupable_parameters);
+    let indicator = spawn_progress_indicator(commands, 16., Vec3::new(0., 36., 0.));
+    commands.entity(indicator).insert(Visibility::Inherited);
+    resources.reserve(building_type.get_building_info().cost);
+    commands.entity(site)
+        .insert((ConstructionSite::new(building_type), Name::new("ConstructionSite")))
+        .add_child(indicator)
+        .observe(release_reservation);
+    return site;
+}
+
+// Whatever the site hadn't picked up yet is free again once it is finished, demolished or destroyed.
+fn release_reservation(
+    trigger: Trigger<OnRemove, ConstructionSite>,
+    sites_query: Query<&ConstructionSite>,
+    mut resources: ResMut<Resources>,
+) {
+    let Ok(site) = sites_query.get(trigger.entity()) else { return; };
+    for (resource_type, reserved) in site.reserved.iter() {
+        resources.release(*resource_type, *reserved);
+    }
+}
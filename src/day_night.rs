@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::{
+    color_correction::PostProcessSettings,
+    looping_float::LoopingFloat,
+    planet::{Planet, PlanetMaterial, Planets, Star},
+};
+
+const DAY_WHITE: Vec3 = Vec3::new(1., 1., 1.);
+const NIGHT_WHITE: Vec3 = Vec3::new(0.42, 0.45, 0.62);
+const DAY_BLACK: Vec3 = Vec3::new(0.024, 0.025, 0.028);
+const NIGHT_BLACK: Vec3 = Vec3::new(0.012, 0.013, 0.024);
+const NIGHT_BRIGHTNESS: f32 = 0.35;
+
+#[derive(Component)]
+pub struct DayCycle {
+    // Only moves the sun on planets without a star to light them, otherwise the planet's spin makes the days.
+    pub day_length: f32,
+    pub sun_degrees: LoopingFloat<360>,
+}
+
+impl DayCycle {
+    pub fn new(day_length: f32) -> DayCycle {
+        DayCycle {
+            day_length,
+            sun_degrees: LoopingFloat::new(0.),
+        }
+    }
+
+    // 1 when the sun is overhead, 0 on the night side, with a short twilight in between.
+    pub fn daylight(&self, position_degrees: f32) -> f32 {
+        let angle = self.sun_degrees.distance(position_degrees).to_radians();
+        return ((angle.cos() + 0.2) / 0.4).clamp(0., 1.);
+    }
+
+    pub fn is_night(&self, position_degrees: f32) -> bool {
+        return self.daylight(position_degrees) < 0.5;
+    }
+}
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (advance_day_cycles, update_planet_lighting, update_camera_lighting).chain());
+    }
+}
+
+fn advance_day_cycles(
+    mut cycles_query: Query<(&mut DayCycle, &Transform)>,
+    stars_query: Query<&Transform, With<Star>>,
+    time: Res<Time>,
+) {
+    for (mut cycle, transform) in cycles_query.iter_mut() {
+        let position = transform.translation.xy();
+        let nearest_star = stars_query.iter()
+            .map(|star| star.translation.xy())
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+        if let Some(star) = nearest_star {
+            // The star's direction in the planet's own frame, clockwise from its up like every position on it.
+            let local = transform.rotation.inverse() * (star - position).extend(0.);
+            cycle.sun_degrees = LoopingFloat::new(local.x.atan2(local.y).to_degrees());
+            continue;
+        }
+        if cycle.day_length <= 0. { continue; }
+        let step = 360. / cycle.day_length * time.delta_secs();
+        cycle.sun_degrees += step;
+    }
+}
+
+fn update_planet_lighting(
+    planets_query: Query<(&MeshMaterial2d<PlanetMaterial>, &DayCycle, &Planet)>,
+    mut materials: ResMut<Assets<PlanetMaterial>>,
+) {
+    for (handle, cycle, planet) in planets_query.iter() {
+        if let Some(material) = materials.get_mut(handle.id()) {
+            material.settings.sun_degrees = cycle.sun_degrees.to_f32();
+            material.settings.night_brightness = (NIGHT_BRIGHTNESS * planet.atmosphere).clamp(0.05, 0.8);
+        }
+    }
+}
+
+fn update_camera_lighting(
+    mut camera_query: Query<(&mut PostProcessSettings, &GlobalTransform), With<Camera>>,
+    planets: Res<Planets>,
+    planets_query: Query<(&GlobalTransform, &Planet, &DayCycle)>,
+) {
+    for (mut settings, camera_transform) in camera_query.iter_mut() {
+        let camera_pos = camera_transform.translation().xy();
+        let mut closest: Option<(f32, f32)> = None;
+        for planet_entity in planets.all.iter() {
+            let Ok((planet_transform, planet, cycle)) = planets_query.get(*planet_entity) else { continue; };
+            let planet_pos = planet_transform.translation().xy();
+            let dist = camera_pos.distance(planet_pos) - planet.radius;
+            if closest.is_some_and(|(best, _)| best <= dist) { continue; }
+            let diff = camera_pos - planet_pos;
+            let angle = if diff.length() < 0.01 { 0. } else { diff.angle_to(planet_transform.up().xy()).to_degrees() };
+            closest = Some((dist, cycle.daylight(LoopingFloat::<360>::new(angle).to_f32())));
+        }
+        let light = closest.map(|(_, light)| light).unwrap_or(1.);
+        settings.white_color = NIGHT_WHITE.lerp(DAY_WHITE, light);
+        settings.black_color = NIGHT_BLACK.lerp(DAY_BLACK, light);
+    }
+}
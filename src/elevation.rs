@@ -0,0 +1,109 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{
+    natural_resource::Biome,
+    planet::{Planet, PlanetBiome, PlanetCrater, PlanetWater},
+    planet_sticker::{Contains, EdgeDistanceTo, PlanetSticker},
+};
+
+// One sample per degree of surface.
+pub const ELEVATION_SAMPLES: usize = 360;
+// How much larger than the planet its quad is drawn, leaving room for the hills.
+pub const TERRAIN_MARGIN: f32 = 1.1;
+// Degrees over which the hills flatten out towards a shore.
+const SHORE_BLEND: f32 = 12.;
+// Degrees over which the ground climbs up to the highlands.
+const HIGHLANDS_RAMP: f32 = 8.;
+const HIGHLANDS_RISE: f32 = 0.05;
+const HILLS_AMPLITUDE: f32 = 0.03;
+
+// The height of the ground above the planet's radius, in world units, shared by the shader and the stickers.
+#[derive(Component, Clone)]
+pub struct PlanetElevation {
+    pub samples: Vec<f32>,
+    // Rolling hills before water, biomes and craters have shaped them.
+    hills: Vec<f32>,
+}
+
+impl PlanetElevation {
+    pub fn hills(radius: f32, seed: u32) -> PlanetElevation {
+        let amplitude = radius * HILLS_AMPLITUDE;
+        let phase = seed as f32 * 1.618;
+        let hills: Vec<f32> = (0..ELEVATION_SAMPLES).map(|index| {
+            let angle = index as f32 / ELEVATION_SAMPLES as f32 * TAU;
+            let wave = (angle * 3. + phase).sin() * 0.5
+                + (angle * 5. + phase * 2.).sin() * 0.3
+                + (angle * 9. + phase * 3.).sin() * 0.2;
+            return (wave + 1.) / 2. * amplitude;
+        }).collect();
+        PlanetElevation {
+            samples: hills.clone(),
+            hills,
+        }
+    }
+
+    pub fn height_at(&self, position_degrees: f32) -> f32 {
+        let position = position_degrees.rem_euclid(360.) / 360. * ELEVATION_SAMPLES as f32;
+        let index = position.floor() as usize % ELEVATION_SAMPLES;
+        let next = (index + 1) % ELEVATION_SAMPLES;
+        return self.samples[index].lerp(self.samples[next], position.fract());
+    }
+}
+
+pub struct ElevationPlugin;
+
+impl Plugin for ElevationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, bake_elevation);
+    }
+}
+
+fn smoothstep(edge: f32, x: f32) -> f32 {
+    let t = (x / edge).clamp(0., 1.);
+    return t * t * (3. - 2. * t);
+}
+
+fn bake_elevation(
+    mut planets_query: Query<(Entity, &Planet, &mut PlanetElevation)>,
+    waters_query: Query<&PlanetSticker, With<PlanetWater>>,
+    biomes_query: Query<(&PlanetSticker, &PlanetBiome)>,
+    craters_query: Query<(&PlanetSticker, &PlanetCrater)>,
+    changed_query: Query<(), (Or<(With<PlanetWater>, With<PlanetBiome>, With<PlanetCrater>)>, Changed<PlanetSticker>)>,
+    mut removed_waters: RemovedComponents<PlanetWater>,
+    mut removed_biomes: RemovedComponents<PlanetBiome>,
+    mut removed_craters: RemovedComponents<PlanetCrater>,
+) {
+    let removed = removed_waters.read().count() + removed_biomes.read().count() + removed_craters.read().count() > 0;
+    let added = planets_query.iter_mut().any(|(_, _, elevation)| elevation.is_added());
+    if !added && changed_query.is_empty() && !removed { return; }
+    for (planet, planet_info, mut elevation) in planets_query.iter_mut() {
+        let highest = planet_info.radius * (TERRAIN_MARGIN - 1.) - 0.5;
+        let on_planet = |sticker: &PlanetSticker| sticker.planet == Some(planet);
+        let mut samples = vec![0.; ELEVATION_SAMPLES];
+        for (index, sample) in samples.iter_mut().enumerate() {
+            let position = index as f32 * 360. / ELEVATION_SAMPLES as f32;
+            // Water stays at the planet's radius, with the ground easing down to it.
+            let mut land: f32 = 1.;
+            for water in waters_query.iter().filter(|water| on_planet(water)) {
+                let outside = water.edge_distance_to(position) * if water.contains(position) { -1. } else { 1. };
+                land = land.min(smoothstep(SHORE_BLEND, outside));
+            }
+            let mut height = elevation.hills[index];
+            for (sticker, biome) in biomes_query.iter().filter(|(sticker, _)| on_planet(sticker)) {
+                if biome.biome != Biome::Highlands || !sticker.contains(position) { continue; }
+                height += planet_info.radius * HIGHLANDS_RISE * smoothstep(HIGHLANDS_RAMP, sticker.edge_distance_to(position));
+            }
+            height *= land;
+            for (sticker, crater) in craters_query.iter().filter(|(sticker, _)| on_planet(sticker)) {
+                let Some(size) = sticker.size_degrees else { continue; };
+                if !sticker.contains(position) { continue; }
+                let r = sticker.position_degrees.distance(position) / (size / 2.);
+                height -= crater.depth * (1. - r * r);
+            }
+            *sample = height.min(highest);
+        }
+        elevation.samples = samples;
+    }
+}
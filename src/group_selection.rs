@@ -0,0 +1,382 @@
+use bevy::prelude::*;
+use bevy_pancam::PanCam;
+
+use crate::{
+    construction::ConstructionSite,
+    mouse_position::MousePosition,
+    natural_resource::NaturalResource,
+    occupable::{set_selection, Occupable, SelectedOccupable, SelectionChanged},
+    planet_placing::Building,
+    planet_sticker::PlanetSticker,
+    planet_villager::{PlanetVillager, VillagerWandering, VillagerWorking},
+    resources::Resources,
+};
+
+#[derive(Resource, Default)]
+pub struct GroupSelection {
+    pub entities: Vec<Entity>,
+    pub assign_count: u32,
+}
+
+#[derive(Resource, Default)]
+struct BoxSelection {
+    start: Option<Vec2>,
+    dragging: bool,
+}
+
+#[derive(Component)]
+struct GroupPanel;
+
+#[derive(Component, Clone, Copy)]
+enum GroupAction {
+    FewerVillagers,
+    MoreVillagers,
+    Assign,
+    UnassignAll,
+    Demolish,
+}
+
+#[derive(Component)]
+struct GroupStats;
+
+#[derive(Component)]
+struct GroupButtonLabel;
+
+pub struct GroupSelectionPlugin;
+
+impl Plugin for GroupSelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GroupSelection { entities: vec![], assign_count: 1 })
+            .init_resource::<BoxSelection>()
+            .add_systems(Startup, spawn_group_panel)
+            .add_systems(Update, (
+                update_pan_buttons,
+                handle_group_clicks,
+                handle_box_selection,
+                prune_group,
+                rebuild_group_panel,
+                handle_group_buttons,
+                update_group_labels,
+                draw_group_markers,
+            ).chain());
+    }
+}
+
+fn is_shift_held(keys: &ButtonInput<KeyCode>) -> bool {
+    return keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+}
+
+// Replaces the group, making sure the single selection and the group never show at the same time.
+fn set_group(
+    group: &mut ResMut<GroupSelection>,
+    selected_occupable: &mut ResMut<SelectedOccupable>,
+    ev_selection: &mut EventWriter<SelectionChanged>,
+    entities: Vec<Entity>,
+) {
+    if !entities.is_empty() {
+        set_selection(selected_occupable, ev_selection, None);
+    }
+    if group.entities != entities {
+        group.entities = entities;
+    }
+}
+
+// Left-drag pans the camera, unless shift is held to drag out a box selection.
+fn update_pan_buttons(
+    keys: Res<ButtonInput<KeyCode>>,
+    box_selection: Res<BoxSelection>,
+    mut camera_query: Query<&mut PanCam>,
+) {
+    let Ok(mut pan_cam) = camera_query.get_single_mut() else { return; };
+    let grab_buttons = if is_shift_held(&keys) || box_selection.dragging {
+        vec![MouseButton::Right, MouseButton::Middle]
+    } else {
+        vec![MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+    };
+    if pan_cam.grab_buttons != grab_buttons {
+        pan_cam.grab_buttons = grab_buttons;
+    }
+}
+
+fn handle_group_clicks(
+    mut events: EventReader<Pointer<Click>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut group: ResMut<GroupSelection>,
+    mut selected_occupable: ResMut<SelectedOccupable>,
+    mut ev_selection: EventWriter<SelectionChanged>,
+    selectable_query: Query<Has<PlanetVillager>, Or<(With<Occupable>, With<Building>, With<PlanetVillager>)>>,
+    windows_query: Query<(), With<Window>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        set_group(&mut group, &mut selected_occupable, &mut ev_selection, vec![]);
+    }
+    let targets: Vec<Entity> = events.read()
+        .filter(|event| event.button == PointerButton::Primary)
+        .map(|event| event.target)
+        .collect();
+    // Villagers let clicks through, so a villager standing in front of a building is the one that was meant.
+    let villager_clicked = targets.iter().any(|target| selectable_query.get(*target).unwrap_or(false));
+    for target in targets {
+        let selectable = selectable_query.contains(target);
+        let villager = selectable_query.get(target).unwrap_or(false);
+        if !selectable && !windows_query.contains(target) { continue; }
+        if !is_shift_held(&keys) {
+            // Plain clicks go back to single selection, see `select_entity_system`.
+            set_group(&mut group, &mut selected_occupable, &mut ev_selection, vec![]);
+            continue;
+        }
+        if !selectable || (villager_clicked && !villager) { continue; }
+        let mut entities = group.entities.clone();
+        if entities.is_empty() {
+            entities.extend(selected_occupable.occupable);
+        }
+        if let Some(index) = entities.iter().position(|entity| *entity == target) {
+            entities.remove(index);
+        } else {
+            entities.push(target);
+        }
+        set_group(&mut group, &mut selected_occupable, &mut ev_selection, entities);
+    }
+}
+
+fn handle_box_selection(
+    mut gizmos: Gizmos,
+    mut press_events: EventReader<Pointer<Down>>,
+    mut drag_start_events: EventReader<Pointer<DragStart>>,
+    mut drag_end_events: EventReader<Pointer<DragEnd>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_position: Res<MousePosition>,
+    mut group: ResMut<GroupSelection>,
+    mut selected_occupable: ResMut<SelectedOccupable>,
+    mut ev_selection: EventWriter<SelectionChanged>,
+    selectable_query: Query<(Entity, &GlobalTransform), Or<(With<Occupable>, With<Building>, With<PlanetVillager>)>>,
+    windows_query: Query<(), With<Window>>,
+    mut box_selection: ResMut<BoxSelection>,
+) {
+    for event in press_events.read() {
+        if event.button != PointerButton::Primary || !windows_query.contains(event.target) || !is_shift_held(&keys) { continue; }
+        box_selection.start = Some(mouse_position.world_position);
+    }
+    for event in drag_start_events.read() {
+        if event.button != PointerButton::Primary || !windows_query.contains(event.target) { continue; }
+        box_selection.dragging = box_selection.start.is_some();
+    }
+    let Some(start) = box_selection.start else { return; };
+    if !box_selection.dragging { return; }
+    let selection_rect = Rect::from_corners(start, mouse_position.world_position);
+    gizmos.rect_2d(Isometry2d::from_translation(selection_rect.center()), selection_rect.size(), Color::WHITE);
+    let mut finished = false;
+    for event in drag_end_events.read() {
+        if event.button == PointerButton::Primary {
+            finished = true;
+        }
+    }
+    if !finished { return; }
+    box_selection.start = None;
+    box_selection.dragging = false;
+    let mut entities = if is_shift_held(&keys) { group.entities.clone() } else { vec![] };
+    for (entity, transform) in selectable_query.iter() {
+        if !selection_rect.contains(transform.translation().xy()) { continue; }
+        if entities.contains(&entity) { continue; }
+        entities.push(entity);
+    }
+    set_group(&mut group, &mut selected_occupable, &mut ev_selection, entities);
+}
+
+fn prune_group(
+    mut group: ResMut<GroupSelection>,
+    selectable_query: Query<(), Or<(With<Occupable>, With<Building>, With<PlanetVillager>)>>,
+) {
+    if group.entities.iter().all(|entity| selectable_query.contains(*entity)) { return; }
+    group.entities.retain(|entity| selectable_query.contains(*entity));
+}
+
+fn spawn_group_panel(mut commands: Commands) {
+    commands.spawn((
+        // Under the resource bar, leaving the bottom left to the building panel.
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(5.),
+            top: Val::Px(48.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.)),
+            row_gap: Val::Px(4.),
+            border: UiRect::all(Val::Px(2.)),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK),
+        BorderColor(Color::WHITE),
+        Visibility::Hidden,
+        GroupPanel,
+        Name::new("GroupPanel"),
+    ));
+}
+
+fn spawn_group_button(parent: &mut ChildBuilder, font: &TextFont, action: GroupAction) {
+    parent.spawn((
+        Button,
+        Node {
+            padding: UiRect::axes(Val::Px(6.), Val::Px(2.)),
+            border: UiRect::all(Val::Px(1.)),
+            ..default()
+        },
+        BorderColor(Color::WHITE),
+        BackgroundColor(Color::BLACK),
+        action,
+    )).with_children(|button| {
+        button.spawn((Text::new(""), font.clone(), GroupButtonLabel));
+    });
+}
+
+fn rebuild_group_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    group: Res<GroupSelection>,
+    mut panel_query: Query<(Entity, &mut Visibility), With<GroupPanel>>,
+    mut shown: Local<Vec<Entity>>,
+) {
+    if *shown == group.entities { return; }
+    *shown = group.entities.clone();
+    let Ok((panel, mut visibility)) = panel_query.get_single_mut() else { return; };
+    commands.entity(panel).despawn_descendants();
+    if group.entities.is_empty() {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+    let font = TextFont {
+        font: asset_server.load("fonts/pixel.ttf"),
+        font_size: 16.0,
+        ..default()
+    };
+    commands.entity(panel).with_children(|parent| {
+        parent.spawn((Text::new(""), font.clone(), GroupStats));
+        parent.spawn(Node {
+            column_gap: Val::Px(4.),
+            ..default()
+        }).with_children(|row| {
+            spawn_group_button(row, &font, GroupAction::FewerVillagers);
+            spawn_group_button(row, &font, GroupAction::Assign);
+            spawn_group_button(row, &font, GroupAction::MoreVillagers);
+        });
+        spawn_group_button(parent, &font, GroupAction::UnassignAll);
+        spawn_group_button(parent, &font, GroupAction::Demolish);
+    });
+}
+
+fn handle_group_buttons(
+    mut commands: Commands,
+    buttons_query: Query<(&Interaction, &GroupAction), Changed<Interaction>>,
+    mut group: ResMut<GroupSelection>,
+    occupables_query: Query<(&Occupable, &PlanetSticker)>,
+    demolishable_query: Query<Option<&ConstructionSite>, Or<(With<Building>, With<ConstructionSite>)>>,
+    wandering_query: Query<(Entity, &PlanetSticker), With<VillagerWandering>>,
+    working_query: Query<(Entity, &VillagerWorking)>,
+    mut resources: ResMut<Resources>,
+) {
+    for (interaction, action) in buttons_query.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        match *action {
+            GroupAction::FewerVillagers => group.assign_count = group.assign_count.saturating_sub(1).max(1),
+            GroupAction::MoreVillagers => group.assign_count += 1,
+            GroupAction::Assign => {
+                let mut open_slots: Vec<(Entity, u32)> = group.entities.iter().filter_map(|entity| {
+                    let (occupable, _) = occupables_query.get(*entity).ok()?;
+                    let workers = working_query.iter().filter(|(_, worker)| worker.current_occupable == *entity).count() as u32;
+                    return Some((*entity, occupable.max_workers.saturating_sub(workers)));
+                }).collect();
+                let mut idle: Vec<(Entity, &PlanetSticker)> = wandering_query.iter().collect();
+                // Spread villagers one at a time across the selection so every occupable gets a share.
+                let mut assigned = 0;
+                while assigned < group.assign_count {
+                    let mut progressed = false;
+                    for (occupable_entity, slots) in open_slots.iter_mut() {
+                        if assigned >= group.assign_count || *slots == 0 { continue; }
+                        let Ok((_, occupable_sticker)) = occupables_query.get(*occupable_entity) else { continue; };
+                        let Some(index) = idle.iter().position(|(_, sticker)| sticker.planet == occupable_sticker.planet) else { continue; };
+                        let (villager_entity, _) = idle.remove(index);
+                        commands
+                            .entity(villager_entity)
+                            .remove::<VillagerWandering>()
+                            .insert(VillagerWorking::new(*occupable_entity));
+                        *slots -= 1;
+                        assigned += 1;
+                        progressed = true;
+                    }
+                    if !progressed { break; }
+                }
+            }
+            GroupAction::UnassignAll => {
+                for (worker_entity, worker) in working_query.iter() {
+                    if !group.entities.contains(&worker.current_occupable) && !group.entities.contains(&worker_entity) { continue; }
+                    commands
+                        .entity(worker_entity)
+                        .remove::<VillagerWorking>()
+                        .insert(VillagerWandering::default());
+                }
+            }
+            GroupAction::Demolish => {
+                for entity in group.entities.iter() {
+                    let Ok(site) = demolishable_query.get(*entity) else { continue; };
+                    // Materials already brought to a construction site go back into storage.
+                    for (resource_type, delivered) in site.iter().flat_map(|site| site.delivered.iter()) {
+                        resources.add(*resource_type, *delivered);
+                    }
+                    commands.entity(*entity).despawn_recursive();
+                }
+            }
+        }
+    }
+}
+
+fn update_group_labels(
+    buttons_query: Query<(&GroupAction, &Children)>,
+    mut labels_query: Query<&mut Text, (With<GroupButtonLabel>, Without<GroupStats>)>,
+    mut stats_query: Query<&mut Text, With<GroupStats>>,
+    group: Res<GroupSelection>,
+    buildings_query: Query<(), Or<(With<Building>, With<ConstructionSite>)>>,
+    natural_resources_query: Query<&NaturalResource>,
+    occupables_query: Query<&Occupable>,
+    villagers_query: Query<(), With<PlanetVillager>>,
+    working_query: Query<&VillagerWorking>,
+) {
+    if group.entities.is_empty() { return; }
+    let buildings = group.entities.iter().filter(|entity| buildings_query.contains(**entity)).count();
+    let villagers = group.entities.iter().filter(|entity| villagers_query.contains(**entity)).count();
+    let natural_resources: Vec<&NaturalResource> = group.entities.iter().filter_map(|entity| natural_resources_query.get(*entity).ok()).collect();
+    let remaining: u32 = natural_resources.iter().map(|natural_resource| natural_resource.amount_remaining).sum();
+    let max_workers: u32 = group.entities.iter().filter_map(|entity| occupables_query.get(*entity).ok()).map(|occupable| occupable.max_workers).sum();
+    let workers = working_query.iter().filter(|worker| group.entities.contains(&worker.current_occupable)).count();
+    for mut stats in stats_query.iter_mut() {
+        stats.0 = format!(
+            "{} selected\nBuildings: {}\nResources: {} ({} left)\nVillagers: {}\nWorkers: {}/{}",
+            group.entities.len(), buildings, natural_resources.len(), remaining, villagers, workers, max_workers,
+        );
+    }
+    for (action, children) in buttons_query.iter() {
+        let label = match *action {
+            GroupAction::FewerVillagers => "-".to_owned(),
+            GroupAction::MoreVillagers => "+".to_owned(),
+            GroupAction::Assign => format!("Assign {}", group.assign_count),
+            GroupAction::UnassignAll => "Unassign all".to_owned(),
+            GroupAction::Demolish => format!("Demolish {}", buildings),
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = labels_query.get_mut(*child) {
+                text.0 = label.clone();
+            }
+        }
+    }
+}
+
+fn draw_group_markers(
+    mut gizmos: Gizmos,
+    group: Res<GroupSelection>,
+    transforms_query: Query<&GlobalTransform>,
+) {
+    for entity in group.entities.iter() {
+        let Ok(transform) = transforms_query.get(*entity) else { continue; };
+        let position = transform.translation().xy() + transform.up().xy() * 8.;
+        gizmos.circle_2d(Isometry2d::from_translation(position), 10., Color::WHITE);
+    }
+}
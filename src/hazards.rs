@@ -0,0 +1,419 @@
+use std::str::FromStr;
+
+use bevy::{prelude::*, sprite::Anchor};
+use rand::Rng;
+
+use crate::{
+    blinking_sprite::BlinkingSprite,
+    colony_rng::{ColonyRng, ColonyRngSet},
+    looping_float::LoopingFloat,
+    natural_resource::NaturalResource,
+    notifications::{ColonyEvent, ColonyEventKind},
+    occupable::ResourceType,
+    pathfinding::{CachedPath, SurfaceGraphs},
+    planet::{OnSurface, Planet, PlanetCrater},
+    planet_placing::PlanetPlacingGhost,
+    planet_sticker::PlanetSticker,
+    planet_villager::{walk_towards, PlanetVillager, PlanetVillagerAnimationState, VillagerFleeing, WORKER_SPEED},
+    spritesheet_animator::SpritesheetAnimator,
+};
+
+// Seconds between a meteor being spotted and it hitting the ground.
+const METEOR_WARNING: f32 = 10.;
+const METEOR_FALL_HEIGHT: f32 = 160.;
+const CRATER_DEPTH: f32 = 4.;
+const BURN_TIME: f32 = 20.;
+const SPREAD_INTERVAL: f32 = 4.;
+// World units along the surface a fire can jump from one tree to the next.
+const SPREAD_DISTANCE: f32 = 30.;
+const SPREAD_CHANCE: f64 = 0.5;
+// World units around a burning tree that villagers keep away from.
+const FIRE_DANGER: f32 = 14.;
+// World units villagers run past the edge of a danger before they stop.
+const FLEE_MARGIN: f32 = 6.;
+// Below this much air nothing burns.
+const MIN_FIRE_ATMOSPHERE: f32 = 0.3;
+
+#[derive(Resource, Clone, Copy, PartialEq, Default)]
+pub enum Difficulty {
+    Peaceful,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    // Average seconds between two meteors, None turns them off.
+    fn meteor_interval(&self) -> Option<f32> {
+        match self {
+            Difficulty::Peaceful => None,
+            Difficulty::Normal => Some(300.),
+            Difficulty::Hard => Some(150.),
+        }
+    }
+
+    // Average seconds between two wildfires, None turns them off.
+    fn fire_interval(&self) -> Option<f32> {
+        match self {
+            Difficulty::Peaceful => None,
+            Difficulty::Normal => Some(360.),
+            Difficulty::Hard => Some(180.),
+        }
+    }
+
+    // World units around the impact that a meteor wipes out.
+    fn meteor_radius(&self) -> f32 {
+        match self {
+            Difficulty::Hard => 28.,
+            _ => 18.,
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Difficulty, String> {
+        match value {
+            "peaceful" => return Ok(Difficulty::Peaceful),
+            "normal" => return Ok(Difficulty::Normal),
+            "hard" => return Ok(Difficulty::Hard),
+            _ => return Err(format!("Unknown difficulty {}", value)),
+        }
+    }
+}
+
+// Seconds until the next hazard of each kind, None while that kind is turned off.
+#[derive(Resource, Default)]
+struct HazardClock {
+    next_meteor: Option<f32>,
+    next_fire: Option<f32>,
+}
+
+#[derive(Component)]
+struct IncomingMeteor {
+    remaining: f32,
+    radius_degrees: f32,
+}
+
+#[derive(Component)]
+struct FallingRock;
+
+#[derive(Component)]
+struct Burning {
+    remaining: f32,
+    spread_in: f32,
+}
+
+pub struct HazardsPlugin;
+
+impl Plugin for HazardsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>()
+            .insert_resource(HazardClock::default())
+            .add_systems(Update, (schedule_hazards, drop_meteors, spread_wildfires, handle_fleeing_villagers).chain().in_set(ColonyRngSet::Hazards));
+    }
+}
+
+// Somewhere between half and one and a half times the average, so hazards don't come like clockwork.
+fn next_in(interval: Option<f32>, colony_rng: &mut ColonyRng) -> Option<f32> {
+    return interval.map(|interval| colony_rng.rng.gen_range(interval * 0.5..interval * 1.5));
+}
+
+fn degrees_for(world_units: f32, planet: &Planet) -> f32 {
+    return (world_units / planet.radius.max(1.)).to_degrees();
+}
+
+fn schedule_hazards(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
+    mut clock: ResMut<HazardClock>,
+    planets_query: Query<(Entity, &Planet)>,
+    trees_query: Query<(Entity, &NaturalResource, &PlanetSticker), Without<Burning>>,
+    mut colony_rng: ResMut<ColonyRng>,
+    mut ev_colony: EventWriter<ColonyEvent>,
+    time: Res<Time>,
+) {
+    if difficulty.is_changed() {
+        clock.next_meteor = next_in(difficulty.meteor_interval(), &mut colony_rng);
+        clock.next_fire = next_in(difficulty.fire_interval(), &mut colony_rng);
+    }
+    let planets: Vec<(Entity, &Planet)> = planets_query.iter().collect();
+    if let Some(next_meteor) = clock.next_meteor.as_mut() {
+        *next_meteor -= time.delta_secs();
+        if *next_meteor <= 0. {
+            clock.next_meteor = next_in(difficulty.meteor_interval(), &mut colony_rng);
+            // Thick air burns most meteors up on the way down, so airless planets get hit the most.
+            let weights: Vec<f32> = planets.iter().map(|(_, planet)| 1. / planet.atmosphere.max(0.1)).collect();
+            let mut pick = colony_rng.rng.gen_range(0.0..weights.iter().sum::<f32>().max(f32::EPSILON));
+            let target = planets.iter().zip(weights.iter()).find(|(_, weight)| {
+                pick -= **weight;
+                return pick <= 0.;
+            });
+            if let Some(((planet, planet_info), _)) = target {
+                let position_degrees = colony_rng.rng.gen_range(0.0..360.0);
+                spawn_meteor(&mut commands, &asset_server, *planet, position_degrees, degrees_for(difficulty.meteor_radius(), planet_info));
+                ev_colony.send(ColonyEvent { kind: ColonyEventKind::MeteorIncoming, planet: *planet, position_degrees });
+            }
+        }
+    }
+    if let Some(next_fire) = clock.next_fire.as_mut() {
+        *next_fire -= time.delta_secs();
+        if *next_fire <= 0. {
+            clock.next_fire = next_in(difficulty.fire_interval(), &mut colony_rng);
+            let trees: Vec<(Entity, &PlanetSticker)> = trees_query.iter()
+                .filter(|(_, resource, _)| resource.produced_resource == ResourceType::Wood && resource.amount_remaining > 0)
+                .filter(|(_, _, sticker)| planets.iter().any(|(planet, planet_info)| sticker.planet == Some(*planet) && planet_info.atmosphere >= MIN_FIRE_ATMOSPHERE))
+                .map(|(tree, _, sticker)| (tree, sticker))
+                .collect();
+            if !trees.is_empty() {
+                let (tree, sticker) = trees[colony_rng.rng.gen_range(0..trees.len())];
+                ignite(&mut commands, &asset_server, tree);
+                if let Some(planet) = sticker.planet {
+                    ev_colony.send(ColonyEvent {
+                        kind: ColonyEventKind::WildfireStarted,
+                        planet,
+                        position_degrees: sticker.position_degrees.to_f32(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn spawn_meteor(commands: &mut Commands, asset_server: &Res<AssetServer>, planet: Entity, position_degrees: f32, radius_degrees: f32) {
+    // The marker sticks to the ground where the meteor will land, the rock comes down towards it.
+    let meteor = commands.spawn((
+        Sprite {
+            image: asset_server.load("ui/icons/warning.png"),
+            anchor: Anchor::BottomCenter,
+            ..default()
+        },
+        Transform::from_xyz(0., 0., 5.),
+        PlanetSticker {
+            planet: Some(planet),
+            position_degrees: LoopingFloat::new(position_degrees),
+            size_degrees: None,
+        },
+        BlinkingSprite { enabled: true },
+        IncomingMeteor { remaining: METEOR_WARNING, radius_degrees },
+        Name::new("IncomingMeteor"),
+    )).id();
+    let rock = commands.spawn((
+        Sprite {
+            image: asset_server.load("hazards/meteor.png"),
+            anchor: Anchor::BottomCenter,
+            ..default()
+        },
+        Transform::from_xyz(0., METEOR_FALL_HEIGHT, 1.),
+        FallingRock,
+        Name::new("Meteor"),
+    )).id();
+    commands.entity(meteor).add_child(rock);
+}
+
+fn ignite(commands: &mut Commands, asset_server: &Res<AssetServer>, tree: Entity) {
+    let fire = commands.spawn((
+        Sprite {
+            image: asset_server.load("hazards/fire.png"),
+            anchor: Anchor::BottomCenter,
+            ..default()
+        },
+        Transform::from_xyz(0., 2., 1.),
+        BlinkingSprite { enabled: true },
+        Name::new("Fire"),
+    )).id();
+    commands.entity(tree)
+        .insert(Burning { remaining: BURN_TIME, spread_in: SPREAD_INTERVAL })
+        .add_child(fire);
+}
+
+fn drop_meteors(
+    mut commands: Commands,
+    mut meteors_query: Query<(Entity, &mut IncomingMeteor, &PlanetSticker)>,
+    mut rocks_query: Query<(&Parent, &mut Transform), With<FallingRock>>,
+    stickers_query: Query<(Entity, &PlanetSticker, Has<PlanetVillager>), (With<Transform>, OnSurface, Without<IncomingMeteor>, Without<PlanetPlacingGhost>)>,
+    mut ev_colony: EventWriter<ColonyEvent>,
+    time: Res<Time>,
+) {
+    for (parent, mut transform) in rocks_query.iter_mut() {
+        let Ok((_, meteor, _)) = meteors_query.get(parent.get()) else { continue; };
+        transform.translation.y = METEOR_FALL_HEIGHT * (meteor.remaining / METEOR_WARNING).max(0.);
+    }
+    for (meteor_entity, mut meteor, meteor_sticker) in meteors_query.iter_mut() {
+        meteor.remaining -= time.delta_secs();
+        if meteor.remaining > 0. { continue; }
+        let Some(planet) = meteor_sticker.planet else { continue; };
+        let mut villagers_lost = 0;
+        for (entity, sticker, villager) in stickers_query.iter() {
+            if sticker.planet != Some(planet) { continue; }
+            if sticker.position_degrees.distance(meteor_sticker.position_degrees.to_f32()) > meteor.radius_degrees { continue; }
+            commands.entity(entity).despawn_recursive();
+            if villager {
+                villagers_lost += 1;
+            }
+        }
+        if villagers_lost > 0 {
+            warn!("A meteor killed {} villagers", villagers_lost);
+        }
+        commands.spawn((
+            PlanetSticker {
+                planet: Some(planet),
+                position_degrees: meteor_sticker.position_degrees,
+                size_degrees: Some(meteor.radius_degrees * 2.),
+            },
+            PlanetCrater { depth: CRATER_DEPTH },
+            Name::new("Crater"),
+        ));
+        commands.entity(meteor_entity).despawn_recursive();
+        ev_colony.send(ColonyEvent {
+            kind: ColonyEventKind::MeteorStruck,
+            planet,
+            position_degrees: meteor_sticker.position_degrees.to_f32(),
+        });
+    }
+}
+
+fn spread_wildfires(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut burning_query: Query<(Entity, &mut Burning, &PlanetSticker)>,
+    trees_query: Query<(Entity, &NaturalResource, &PlanetSticker), Without<Burning>>,
+    planets_query: Query<&Planet>,
+    mut colony_rng: ResMut<ColonyRng>,
+    time: Res<Time>,
+) {
+    let mut ignited: Vec<Entity> = vec![];
+    for (tree, mut burning, sticker) in burning_query.iter_mut() {
+        burning.remaining -= time.delta_secs();
+        if burning.remaining <= 0. {
+            commands.entity(tree).despawn_recursive();
+            continue;
+        }
+        burning.spread_in -= time.delta_secs();
+        if burning.spread_in > 0. { continue; }
+        burning.spread_in += SPREAD_INTERVAL;
+        let Some(planet) = sticker.planet.and_then(|planet| planets_query.get(planet).ok()) else { continue; };
+        for (other_tree, resource, other_sticker) in trees_query.iter() {
+            if resource.produced_resource != ResourceType::Wood || other_sticker.planet != sticker.planet { continue; }
+            if ignited.contains(&other_tree) { continue; }
+            if sticker.position_degrees.arc_distance(other_sticker.position_degrees.to_f32(), planet.radius) > SPREAD_DISTANCE { continue; }
+            if !colony_rng.rng.gen_bool(SPREAD_CHANCE) { continue; }
+            ignite(&mut commands, &asset_server, other_tree);
+            ignited.push(other_tree);
+        }
+    }
+}
+
+// The two ends of the stretch of danger around `center`, merged with every danger it overlaps
+// so running to either end doesn't lead into the next one. None when danger covers the whole planet.
+fn escape_edges(center: LoopingFloat<360>, reach: f32, arcs: &[(LoopingFloat<360>, f32)]) -> Option<(LoopingFloat<360>, LoopingFloat<360>)> {
+    let mut low = center - reach;
+    let mut high = center + reach;
+    let mut span = reach * 2.;
+    let mut grown = true;
+    while grown && span < 360. {
+        grown = false;
+        for (other_center, other_reach) in arcs.iter() {
+            if other_center.distance(low.to_f32()) < *other_reach {
+                let extra = -low.difference((*other_center - *other_reach).to_f32());
+                if extra > 0.01 {
+                    low = *other_center - *other_reach;
+                    span += extra;
+                    grown = true;
+                }
+            }
+            if other_center.distance(high.to_f32()) < *other_reach {
+                let extra = high.difference((*other_center + *other_reach).to_f32());
+                if extra > 0.01 {
+                    high = *other_center + *other_reach;
+                    span += extra;
+                    grown = true;
+                }
+            }
+        }
+    }
+    if span >= 360. { return None; }
+    return Some((low, high));
+}
+
+fn handle_fleeing_villagers(
+    mut commands: Commands,
+    mut villager_query: Query<(
+        Entity,
+        &mut PlanetSticker,
+        &mut Sprite,
+        &mut Visibility,
+        &mut SpritesheetAnimator,
+        &mut CachedPath,
+        Has<VillagerFleeing>,
+    ), With<PlanetVillager>>,
+    meteors_query: Query<(&IncomingMeteor, &PlanetSticker), Without<PlanetVillager>>,
+    fires_query: Query<&PlanetSticker, (With<Burning>, Without<PlanetVillager>)>,
+    planets_query: Query<&Planet>,
+    graphs: Res<SurfaceGraphs>,
+    time: Res<Time>,
+) {
+    // Every spot to keep away from, as a planet, a center and a radius in degrees.
+    let mut dangers: Vec<(Entity, LoopingFloat<360>, f32)> = vec![];
+    for (meteor, sticker) in meteors_query.iter() {
+        let Some(planet) = sticker.planet else { continue; };
+        dangers.push((planet, sticker.position_degrees, meteor.radius_degrees));
+    }
+    for sticker in fires_query.iter() {
+        let Some((planet, planet_info)) = sticker.planet.and_then(|planet| planets_query.get(planet).ok().map(|info| (planet, info))) else { continue; };
+        dangers.push((planet, sticker.position_degrees, degrees_for(FIRE_DANGER, planet_info)));
+    }
+    for (villager, sticker, sprite, mut visibility, mut animator, mut cached_path, fleeing) in villager_query.iter_mut() {
+        let Some(planet) = sticker.planet else { continue; };
+        let Ok(planet_info) = planets_query.get(planet) else { continue; };
+        let margin = degrees_for(FLEE_MARGIN, planet_info);
+        let position = sticker.position_degrees.to_f32();
+        // Villagers that got away wait at a safe distance until the danger is over, rather than wandering back into it.
+        let keep_away = if fleeing { margin * 2. } else { margin };
+        let danger = dangers.iter()
+            .find(|(danger_planet, center, radius)| *danger_planet == planet && center.distance(position) < radius + keep_away);
+        let Some((_, center, radius)) = danger else {
+            if fleeing {
+                commands.entity(villager).remove::<VillagerFleeing>();
+            }
+            continue;
+        };
+        if !fleeing {
+            commands.entity(villager).insert(VillagerFleeing);
+        }
+        let arcs: Vec<(LoopingFloat<360>, f32)> = dangers.iter()
+            .filter(|(danger_planet, _, _)| *danger_planet == planet)
+            .map(|(_, center, radius)| (*center, radius + margin))
+            .collect();
+        let Some((low, high)) = escape_edges(*center, radius + margin, &arcs) else { continue; };
+        let escape = [low, high].into_iter()
+            .filter_map(|edge| graphs.find_path(&sticker, edge).map(|path| (edge, path.cost)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        // Nowhere to run, so stay put and hope for the best.
+        let Some((escape, _)) = escape else { continue; };
+        *visibility = Visibility::Visible;
+        animator.current_animation_index = PlanetVillagerAnimationState::Idle as u32;
+        walk_towards(&mut animator, sticker, sprite, &graphs, &mut cached_path, time.delta_secs(), escape, WORKER_SPEED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_edges_skip_overlapping_dangers_across_the_seam() {
+        let arcs = [(LoopingFloat::new(0.), 10.), (LoopingFloat::new(15.), 10.), (LoopingFloat::new(340.), 15.)];
+        let (low, high) = escape_edges(arcs[0].0, arcs[0].1, &arcs).unwrap();
+        assert!((low.to_f32() - 325.).abs() < 0.001);
+        assert!((high.to_f32() - 25.).abs() < 0.001);
+    }
+
+    #[test]
+    fn no_escape_when_danger_covers_the_planet() {
+        let arcs: Vec<(LoopingFloat<360>, f32)> = (0..6).map(|index| (LoopingFloat::new(index as f32 * 60.), 35.)).collect();
+        assert!(escape_edges(arcs[0].0, arcs[0].1, &arcs).is_none());
+    }
+}
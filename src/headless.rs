@@ -0,0 +1,135 @@
+use std::{fmt::Write as _, path::PathBuf, time::Duration};
+
+use bevy::{app::ScheduleRunnerPlugin, prelude::*, time::TimeUpdateStrategy};
+
+use crate::{
+    hazards::Difficulty,
+    occupable::{Occupable, OccupableType, ResourceType},
+    planet_villager::{PlanetVillager, VillagerSleeping, VillagerWandering, VillagerWorking},
+    resources::Resources,
+};
+
+const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+pub struct LaunchOptions {
+    pub headless: Option<HeadlessPlugin>,
+    pub seed: Option<u64>,
+    pub difficulty: Difficulty,
+}
+
+impl LaunchOptions {
+    // space_colony [--headless] [--minutes N] [--seed S] [--output stats.csv] [--difficulty peaceful|normal|hard] [--sample-interval SECONDS]
+    pub fn from_args() -> Result<LaunchOptions, String> {
+        let mut headless = false;
+        let mut minutes = 10.;
+        let mut sample_interval = TICK.as_secs_f32();
+        let mut seed = None;
+        let mut difficulty = Difficulty::default();
+        let mut output = PathBuf::from("colony_stats.csv");
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => headless = true,
+                "--minutes" => minutes = parse_value(&arg, args.next())?,
+                "--seed" => seed = Some(parse_value(&arg, args.next())?),
+                "--output" => output = parse_value(&arg, args.next())?,
+                "--difficulty" => difficulty = parse_value(&arg, args.next())?,
+                "--sample-interval" => sample_interval = parse_value(&arg, args.next())?,
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+        return Ok(LaunchOptions {
+            headless: if headless { Some(HeadlessPlugin { minutes, output, sample_interval }) } else { None },
+            seed,
+            difficulty,
+        });
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(arg: &str, value: Option<String>) -> Result<T, String> {
+    let Some(value) = value else { return Err(format!("Missing value for {}", arg)); };
+    return value.parse().map_err(|_| format!("Invalid value {} for {}", value, arg));
+}
+
+#[derive(Resource)]
+struct HeadlessRecorder {
+    duration: f32,
+    output: PathBuf,
+    // A row every this many ticks.
+    sample_ticks: u32,
+    ticks: u32,
+    csv: String,
+}
+
+pub struct HeadlessPlugin {
+    pub minutes: f32,
+    pub output: PathBuf,
+    pub sample_interval: f32,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let mut csv = "seconds".to_owned();
+        for resource_type in ResourceType::ALL {
+            let _ = write!(csv, ",{:?}", resource_type);
+        }
+        csv.push_str(",population,idle,sleeping");
+        for occupable_type in OccupableType::ALL {
+            let _ = write!(csv, ",{:?}_workers", occupable_type);
+        }
+        csv.push('\n');
+        // Fixed steps so a run simulates the same amount of time whatever the machine speed.
+        app.add_plugins(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+            .insert_resource(HeadlessRecorder {
+                duration: self.minutes * 60.,
+                output: self.output.clone(),
+                sample_ticks: ((self.sample_interval / TICK.as_secs_f32()).round() as u32).max(1),
+                ticks: 0,
+                csv,
+            })
+            .add_systems(Last, record_tick);
+    }
+}
+
+fn record_tick(
+    mut recorder: ResMut<HeadlessRecorder>,
+    mut ev_exit: EventWriter<AppExit>,
+    time: Res<Time>,
+    resources: Res<Resources>,
+    villagers_query: Query<(Option<&VillagerWorking>, Has<VillagerWandering>, Has<VillagerSleeping>), With<PlanetVillager>>,
+    occupables_query: Query<&Occupable>,
+) {
+    let elapsed = time.elapsed_secs();
+    if recorder.ticks.is_multiple_of(recorder.sample_ticks) {
+        let mut row = format!("{:.3}", elapsed);
+        for resource_type in ResourceType::ALL {
+            let _ = write!(row, ",{}", resources.amount(resource_type));
+        }
+        let population = villagers_query.iter().count();
+        let idle = villagers_query.iter().filter(|(_, wandering, _)| *wandering).count();
+        let sleeping = villagers_query.iter().filter(|(_, _, sleeping)| *sleeping).count();
+        let _ = write!(row, ",{},{},{}", population, idle, sleeping);
+        for occupable_type in OccupableType::ALL {
+            let workers = villagers_query.iter()
+                .filter_map(|(worker, _, _)| worker)
+                .filter(|worker| occupables_query.get(worker.current_occupable).is_ok_and(|occupable| occupable.occupable_type == occupable_type))
+                .count();
+            let _ = write!(row, ",{}", workers);
+        }
+        row.push('\n');
+        recorder.csv.push_str(&row);
+    }
+    recorder.ticks += 1;
+    if elapsed < recorder.duration { return; }
+    match std::fs::write(&recorder.output, &recorder.csv) {
+        Ok(()) => {
+            info!("Wrote colony statistics to {}", recorder.output.display());
+            ev_exit.send(AppExit::Success);
+        }
+        Err(error) => {
+            error!("Could not write {}: {}", recorder.output.display(), error);
+            ev_exit.send(AppExit::error());
+        }
+    }
+}
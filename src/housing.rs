@@ -0,0 +1,14 @@
+use bevy::prelude::*;
+
+use crate::structure::spawn_structure;
+
+#[derive(Component)]
+pub struct Housing {
+    pub beds: u32,
+}
+
+pub fn spawn_house(commands: &mut Commands, texture: Handle<Image>, planet: Entity, position_degrees: f32, beds: u32) -> Entity {
+    let house = spawn_structure(commands, texture, planet, position_degrees, "House");
+    commands.entity(house).insert(Housing { beds });
+    return house;
+}
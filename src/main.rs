@@ -9,12 +9,15 @@ use background::BackgroundPlugin;
 use bevy_pancam::{PanCam, PanCamPlugin};
 use blinking_sprite::BlinkingSpritePlugin;
 use color_correction::{PostProcessPlugin, PostProcessSettings};
+use day_night::{DayCycle, DayNightPlugin};
+use elevation::{ElevationPlugin, PlanetElevation, TERRAIN_MARGIN};
 use looping_float::LoopingFloat;
 use mouse_position::MousePositionPlugin;
-use natural_resource::{spawn_bush, spawn_tree, NaturalResourcePlugin};
+use natural_resource::{spawn_bush, Biome, spawn_ore_vein, spawn_rock, spawn_tree, NaturalResourcePlugin};
 use noisy_bevy::NoisyShaderPlugin;
 use occupable::*;
 use occupables::*;
+mod pathfinding;
 mod planet;
 mod planet_sticker;
 mod planet_villager;
@@ -27,23 +30,69 @@ mod background;
 mod mouse_position;
 mod blinking_sprite;
 mod natural_resource;
+mod notifications;
 mod scaling_sprite;
+mod colony_rng;
 mod color_correction;
+mod construction;
+mod day_night;
+mod elevation;
+mod group_selection;
+mod hazards;
+mod headless;
+mod housing;
+mod bridge;
+mod building_panel;
+mod camera_control;
+mod upgrades;
+mod processing;
+mod selection_overlay;
+mod progress_indicator;
+mod statistics;
+mod storage;
+mod structure;
+mod stuck_workers;
 
 use bevy::{
-    prelude::*, sprite::Material2dPlugin, window::PresentMode
+    prelude::*, render::{settings::WgpuSettings, storage::ShaderStorageBuffer, RenderPlugin}, sprite::Material2dPlugin, window::{ExitCondition, PresentMode}, winit::WinitPlugin
 };
-use planet::{PlanetMaterial, PlanetSettings, PlanetWater, Planets};
-use planet_placing::CircleMaterial;
+use colony_rng::{ColonyRng, ColonyRngSet};
+use headless::LaunchOptions;
+use planet::{Orbit, PlanetBiome, PlanetCrater, PlanetMaterial, PlanetMotion, PlanetSettings, PlanetWater, Planets, Star};
+use planet_placing::{BuildingType, RangeArcMaterial};
 use planet_sticker::PlanetSticker;
 use planet_villager::spawn_villager;
 use resources::ResourcesPlugin;
 use scaling_sprite::ScalingSpritePlugin;
 use ui::CustomUiPlugin;
 
-fn main() {
-    App::new()
-        .add_plugins(
+fn main() -> AppExit {
+    let options = match LaunchOptions::from_args() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            return AppExit::error();
+        }
+    };
+    let mut app = App::new();
+    if let Some(headless) = options.headless {
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings { backends: None, ..default() }.into(),
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest())
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins(headless);
+    } else {
+        app.add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
                     primary_window: Some(Window {
@@ -53,7 +102,11 @@ fn main() {
                     ..default()
                 })
                 .set(ImagePlugin::default_nearest()),
-        )
+        );
+    }
+    app.insert_resource(ColonyRng::new(options.seed))
+        .insert_resource(options.difficulty)
+        .configure_sets(Update, (ColonyRngSet::Resources, ColonyRngSet::Villagers, ColonyRngSet::Spawn, ColonyRngSet::Hazards).chain())
         .add_plugins((
             planet_sticker::PlanetStickerPlugin,
             planet_villager::PlanetVillagerPlugin,
@@ -71,14 +124,17 @@ fn main() {
             NaturalResourcePlugin,
             ScalingSpritePlugin,
         ))
+        .add_plugins((DayNightPlugin, processing::ProcessingPlugin, progress_indicator::ProgressIndicatorPlugin, building_panel::BuildingPanelPlugin, upgrades::UpgradesPlugin))
+        .add_plugins((construction::ConstructionPlugin, selection_overlay::SelectionOverlayPlugin, group_selection::GroupSelectionPlugin, statistics::StatisticsPlugin, pathfinding::PathfindingPlugin, stuck_workers::StuckWorkersPlugin))
+        .add_plugins((notifications::NotificationsPlugin, camera_control::CameraControlPlugin, ElevationPlugin, hazards::HazardsPlugin))
         .add_plugins(NoisyShaderPlugin)
         .add_plugins(PostProcessPlugin)
         .add_plugins(PanCamPlugin::default())
         .add_plugins(bevy::diagnostic::FrameTimeDiagnosticsPlugin)
-        .add_plugins((UiMaterialPlugin::<ui::ProgressBarMaterial>::default(), Material2dPlugin::<background::StarsMaterial>::default(), Material2dPlugin::<CircleMaterial>::default()))
+        .add_plugins((UiMaterialPlugin::<ui::ProgressBarMaterial>::default(), Material2dPlugin::<background::StarsMaterial>::default(), Material2dPlugin::<RangeArcMaterial>::default()))
         .add_systems(Startup, setup)
         .add_event::<occupable::OccupancyChange>()
-        .run();
+        .run()
 }
 
 fn setup(
@@ -87,6 +143,8 @@ fn setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut planets: ResMut<Planets>,
     mut planet_materials: ResMut<Assets<PlanetMaterial>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
 ) {
     commands.spawn((
         Camera2d,
@@ -102,6 +160,8 @@ fn setup(
         },
         PanCam {
             enabled: true,
+            // The left button stops panning while shift is held for box selection, see `group_selection`.
+            grab_buttons: vec![MouseButton::Left, MouseButton::Right, MouseButton::Middle],
             ..default()
         },
         Name::new("Camera"),
@@ -109,26 +169,89 @@ fn setup(
     ));
 
 
-    let main_planet = commands.spawn((
-        Mesh2d(meshes.add(Rectangle{half_size: Vec2::splat(100.)})),
-        MeshMaterial2d(
-            planet_materials.add(PlanetMaterial { 
-                settings: PlanetSettings {
-                    hole_array: [Vec4::splat(0.); 8]
-                }
-            })),
-        Transform::from_xyz(0.0, 0.0, -10.0),
-        planet::Planet { radius: 100. },
-        Name::new("MainPlanet")
-    )).id();
+    commands.spawn((
+        Mesh2d(meshes.add(Circle::new(120.))),
+        MeshMaterial2d(color_materials.add(Color::WHITE)),
+        Transform::from_xyz(0.0, 2400.0, -20.0),
+        Star,
+        Name::new("Sun")
+    ));
+
+    let main_planet = spawn_planet(
+        &mut commands,
+        &mut meshes,
+        &mut planet_materials,
+        &mut buffers,
+        planet::Planet { radius: 100., gravity: 1., atmosphere: 1. },
+        Vec3::new(0.0, 0.0, -10.0),
+        "MainPlanet",
+    );
+    commands.entity(main_planet).insert((
+        PlanetMotion {
+            spin_period: 240.,
+            ..default()
+        },
+        PlanetElevation::hills(100., 1),
+    ));
     planets.main = Some(main_planet);
     planets.all.push(main_planet);
+
+    // Light and airless, quick to cross but dark at night.
+    let moon = spawn_planet(
+        &mut commands,
+        &mut meshes,
+        &mut planet_materials,
+        &mut buffers,
+        planet::Planet { radius: 30., gravity: 0.3, atmosphere: 0.2 },
+        Vec3::new(0.0, 360.0, -10.0),
+        "Moon",
+    );
+    commands.entity(moon).insert((
+        PlanetMotion {
+            spin_period: 90.,
+            spin_degrees: LoopingFloat::new(0.),
+            orbit: Some(Orbit {
+                center: main_planet,
+                distance: 360.,
+                period: 600.,
+                degrees: LoopingFloat::new(0.),
+            }),
+        },
+        PlanetElevation::hills(30., 2),
+    ));
+    planets.all.push(moon);
+    spawn_rock(&mut commands, &asset_server, moon, 90.);
+
+    // Roomy but heavy, every walk takes a while.
+    let giant = spawn_planet(
+        &mut commands,
+        &mut meshes,
+        &mut planet_materials,
+        &mut buffers,
+        planet::Planet { radius: 180., gravity: 1.8, atmosphere: 1.6 },
+        Vec3::new(-900.0, -200.0, -10.0),
+        "Giant",
+    );
+    commands.entity(giant).insert((
+        PlanetMotion {
+            spin_period: 480.,
+            ..default()
+        },
+        PlanetElevation::hills(180., 3),
+    ));
+    planets.all.push(giant);
+    spawn_tree(&mut commands, &asset_server, giant, 40.);
+    spawn_rock(&mut commands, &asset_server, giant, 160.);
     for tree_index in 0..1 {
         spawn_tree(&mut commands, &asset_server, main_planet, tree_index as f32 * 180.);
     }
     for bush_index in 0..1 {
         spawn_bush(&mut commands, &asset_server, main_planet, (bush_index + 1) as f32 * 33.)
     }
+    spawn_building(&mut commands, &asset_server, main_planet, 320., BuildingType::House);
+    spawn_building(&mut commands, &asset_server, main_planet, 340., BuildingType::Storage);
+    spawn_rock(&mut commands, &asset_server, main_planet, 200.);
+    spawn_ore_vein(&mut commands, &asset_server, main_planet, 215.);
     for villager_index in 0..1 {
         spawn_villager(&mut commands, &asset_server, main_planet, 30. + 45. * (villager_index as f32), villager_index.to_string())
     }
@@ -142,6 +265,33 @@ fn setup(
         Name::new("Water")
     )
     });
+    commands.spawn((
+        PlanetSticker {
+            planet: Some(main_planet),
+            position_degrees: LoopingFloat::new(205.),
+            size_degrees: Some(40.)
+        },
+        PlanetBiome { biome: Biome::Highlands },
+        Name::new("Highlands")
+    ));
+    commands.spawn((
+        PlanetSticker {
+            planet: Some(main_planet),
+            position_degrees: LoopingFloat::new(260.),
+            size_degrees: Some(14.)
+        },
+        PlanetCrater { depth: 4. },
+        Name::new("Crater")
+    ));
+    commands.spawn((
+        PlanetSticker {
+            planet: Some(moon),
+            position_degrees: LoopingFloat::new(220.),
+            size_degrees: Some(40.)
+        },
+        PlanetCrater { depth: 3. },
+        Name::new("Crater")
+    ));
     
     /* 
     commands.spawn({(
@@ -164,4 +314,36 @@ fn setup(
     */
 
      
-}
\ No newline at end of file
+}
+
+fn spawn_planet(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    planet_materials: &mut ResMut<Assets<PlanetMaterial>>,
+    buffers: &mut ResMut<Assets<ShaderStorageBuffer>>,
+    planet: planet::Planet,
+    position: Vec3,
+    name: &'static str,
+) -> Entity {
+    let radius = planet.radius;
+    let extent = radius * TERRAIN_MARGIN;
+    return commands.spawn((
+        Mesh2d(meshes.add(Rectangle{half_size: Vec2::splat(extent)})),
+        MeshMaterial2d(
+            planet_materials.add(PlanetMaterial { 
+                settings: PlanetSettings {
+                    radius,
+                    extent,
+                    feature_count: 0,
+                    sun_degrees: 0.,
+                    night_brightness: 1.,
+                },
+                features: buffers.add(planet::new_features_buffer()),
+                elevation: buffers.add(planet::new_elevation_buffer()),
+            })),
+        Transform::from_translation(position),
+        planet,
+        DayCycle::new(240.),
+        Name::new(name)
+    )).id();
+}
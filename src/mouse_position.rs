@@ -19,8 +19,8 @@ fn update_mouse_position(
     camera_query: Query<(&Camera, &GlobalTransform)>,
     window_query: Query<&Window>,
 ) {
-    let (camera, camera_transform) = camera_query.single();
-    let window = window_query.single();
+    let Ok((camera, camera_transform)) = camera_query.get_single() else { return; };
+    let Ok(window) = window_query.get_single() else { return; };
 
     // check if the cursor is inside the window and get its position
     // then, ask bevy to convert into world coordinates, and truncate to discard Z
@@ -3,7 +3,7 @@ use std::{f32::INFINITY, time::Duration};
 use bevy::{prelude::*, time::common_conditions::on_timer};
 use rand::Rng;
 
-use crate::{blinking_sprite::BlinkingSprite, planet::{Planet, PlanetWater}, planet_sticker::{Contains, EdgeDistanceTo, IsCollidingWith, PlanetSticker}, scaling_sprite::ScalingSprite, spawn_occupable, Occupable, OccupableParameters, OccupableType, ResourceType};
+use crate::{colony_rng::{ColonyRng, ColonyRngSet}, blinking_sprite::BlinkingSprite, notifications::{ColonyEvent, ColonyEventKind}, planet::{OnSurface, Planet, PlanetBiome, PlanetWater}, planet_sticker::{Contains, EdgeDistanceTo, IsCollidingWith, PlanetSticker}, scaling_sprite::ScalingSprite, spawn_occupable, Occupable, OccupableParameters, OccupableType, ResourceType};
 
 #[derive(Component, PartialEq)]
 pub struct NaturalResource {
@@ -11,10 +11,12 @@ pub struct NaturalResource {
     pub amount_remaining: u32,
 }
 
+#[derive(PartialEq, Clone, Copy)]
 pub enum Biome {
     Water,
     Swamp,
-    Ground
+    Ground,
+    Highlands,
 }
 
 pub struct NaturalResourcePlugin;
@@ -22,11 +24,16 @@ pub struct NaturalResourcePlugin;
 impl Plugin for NaturalResourcePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, handle_natural_resources)
-        .add_systems(Update,handle_spawning_resources.run_if(on_timer(Duration::from_secs_f32(500.))));
+        .add_systems(Update,handle_spawning_resources.run_if(on_timer(Duration::from_secs_f32(500.))).in_set(ColonyRngSet::Resources));
     }
 }
 
-fn determine_biome(planet: Entity, pos: f32, waters_query: &Query<&PlanetSticker, With<PlanetWater>>) -> Option<Biome> {
+pub fn determine_biome(
+    planet: Entity,
+    pos: f32,
+    waters_query: &Query<&PlanetSticker, With<PlanetWater>>,
+    biomes_query: &Query<(&PlanetSticker, &PlanetBiome)>,
+) -> Option<Biome> {
     let mut closest = INFINITY;
     for water in waters_query.iter() {
         if water.planet != Some(planet) { continue; };
@@ -38,9 +45,18 @@ fn determine_biome(planet: Entity, pos: f32, waters_query: &Query<&PlanetSticker
             closest = dist;
         }
     }
+    // Biomes laid out by hand or by generation win over the distance to water.
+    for (sticker, biome) in biomes_query.iter() {
+        if sticker.planet == Some(planet) && sticker.contains(pos) {
+            return Some(biome.biome);
+        }
+    }
     if closest < 16. {
         return Some(Biome::Swamp);
     }
+    if closest > 90. {
+        return Some(Biome::Highlands);
+    }
     return Some(Biome::Ground);
 }
 
@@ -49,11 +65,13 @@ fn handle_spawning_resources(
     asset_server: Res<AssetServer>,
     planets_query: Query<Entity, With<Planet>>,
     waters_query: Query<&PlanetSticker, With<PlanetWater>>,
-    stickers_query: Query<&PlanetSticker, Without<PlanetWater>>
+    biomes_query: Query<(&PlanetSticker, &PlanetBiome)>,
+    stickers_query: Query<&PlanetSticker, OnSurface>,
+    mut colony_rng: ResMut<ColonyRng>,
 ) {
     for planet_entity in planets_query.iter() {
-        let pos = rand::thread_rng().gen_range(0.0..360.0);
-        let Some(biome) = determine_biome(planet_entity, pos, &waters_query) else { continue; };
+        let pos = colony_rng.rng.gen_range(0.0..360.0);
+        let Some(biome) = determine_biome(planet_entity, pos, &waters_query, &biomes_query) else { continue; };
         let mut found = false;
         for sticker in stickers_query.iter() {
             if sticker.is_colliding_with_pos(pos, 8.) {
@@ -66,16 +84,34 @@ fn handle_spawning_resources(
             Biome::Water => spawn_fish(&mut commands, &asset_server, planet_entity, pos),
             Biome::Swamp => spawn_bush(&mut commands, &asset_server, planet_entity, pos),
             Biome::Ground => spawn_tree(&mut commands, &asset_server, planet_entity, pos),
+            Biome::Highlands => {
+                if colony_rng.rng.gen_bool(0.3) {
+                    spawn_ore_vein(&mut commands, &asset_server, planet_entity, pos)
+                } else {
+                    spawn_rock(&mut commands, &asset_server, planet_entity, pos)
+                }
+            }
         }
     }
 }
 
 fn handle_natural_resources (
     mut commands: Commands,
-    mut natural_resource_query: Query<(Entity, &NaturalResource, &Transform, &mut ScalingSprite)>
+    mut natural_resource_query: Query<(Entity, &NaturalResource, &Transform, &PlanetSticker, &mut ScalingSprite, Has<Occupable>)>,
+    mut ev_colony: EventWriter<ColonyEvent>,
 ) {
-    for (natural_resource_entity, natural_resource, transform, mut scaling) in natural_resource_query.iter_mut() {
+    for (natural_resource_entity, natural_resource, transform, sticker, mut scaling, occupable) in natural_resource_query.iter_mut() {
         if natural_resource.amount_remaining <= 0 {
+            // Still occupable means this is the first frame it ran out.
+            if occupable {
+                if let Some(planet) = sticker.planet {
+                    ev_colony.send(ColonyEvent {
+                        kind: ColonyEventKind::ResourceDepleted(natural_resource.produced_resource),
+                        planet,
+                        position_degrees: sticker.position_degrees.to_f32(),
+                    });
+                }
+            }
             scaling.target_scale = Vec3::ZERO;
             commands.entity(natural_resource_entity).despawn_descendants();
             commands.entity(natural_resource_entity).remove::<Occupable>();
@@ -159,4 +195,47 @@ pub fn spawn_fish(
         ResourceType::Food,
         10,
     );
-}
\ No newline at end of file
+}
+pub fn spawn_rock(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    planet: Entity,
+    position_degrees: f32,
+) {
+    spawn_natural_resource(
+        commands,
+        OccupableParameters::new(
+            asset_server.load("environment/rock.png"),
+            planet,
+            position_degrees,
+            OccupableType::Mining,
+            2,
+            8.,
+            bevy::sprite::Anchor::BottomCenter
+        ),
+        ResourceType::Stone,
+        15,
+    );
+}
+
+pub fn spawn_ore_vein(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    planet: Entity,
+    position_degrees: f32,
+) {
+    spawn_natural_resource(
+        commands,
+        OccupableParameters::new(
+            asset_server.load("environment/ore.png"),
+            planet,
+            position_degrees,
+            OccupableType::Mining,
+            1,
+            8.,
+            bevy::sprite::Anchor::BottomCenter
+        ),
+        ResourceType::IronOre,
+        8,
+    );
+}
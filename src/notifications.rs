@@ -0,0 +1,188 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    camera_control::{CameraController, CameraMode},
+    occupable::ResourceType,
+    planet_placing::{BuildingType, GetBuildingInfo},
+};
+
+const MAX_ENTRIES: usize = 6;
+const ENTRY_LIFETIME: f32 = 20.;
+
+#[derive(Clone, Copy)]
+pub enum ColonyEventKind {
+    VillagerBorn,
+    ResourceDepleted(ResourceType),
+    BuildingFinished(BuildingType),
+    WorkUnreachable,
+    MeteorIncoming,
+    MeteorStruck,
+    WildfireStarted,
+}
+
+impl ColonyEventKind {
+    fn message(&self) -> String {
+        match self {
+            ColonyEventKind::VillagerBorn => "A villager was born".to_owned(),
+            ColonyEventKind::ResourceDepleted(resource_type) => format!("{:?} source depleted", resource_type),
+            ColonyEventKind::BuildingFinished(building_type) => format!("{} finished", building_type.get_building_info().name),
+            ColonyEventKind::WorkUnreachable => "Workers cannot reach their work".to_owned(),
+            ColonyEventKind::MeteorIncoming => "A meteor is coming down".to_owned(),
+            ColonyEventKind::MeteorStruck => "A meteor struck".to_owned(),
+            ColonyEventKind::WildfireStarted => "A wildfire broke out".to_owned(),
+        }
+    }
+
+    fn icon_path(&self) -> &'static str {
+        match self {
+            ColonyEventKind::VillagerBorn => "ui/icons/villager.png",
+            ColonyEventKind::ResourceDepleted(resource_type) => match resource_type {
+                ResourceType::Food => "ui/icons/food.png",
+                ResourceType::Wood => "ui/icons/wood.png",
+                ResourceType::Planks => "ui/icons/planks.png",
+                ResourceType::Meals => "ui/icons/meals.png",
+                ResourceType::Stone => "ui/icons/stone.png",
+                ResourceType::IronOre => "ui/icons/iron_ore.png",
+            },
+            ColonyEventKind::BuildingFinished(building_type) => building_type.get_building_info().texture_path,
+            ColonyEventKind::WorkUnreachable => "ui/icons/warning.png",
+            ColonyEventKind::MeteorIncoming | ColonyEventKind::MeteorStruck => "hazards/meteor.png",
+            ColonyEventKind::WildfireStarted => "hazards/fire.png",
+        }
+    }
+}
+
+// Events keep where they happened rather than the entity, which may well be gone by the time the entry is clicked.
+#[derive(Event, Clone, Copy)]
+pub struct ColonyEvent {
+    pub kind: ColonyEventKind,
+    pub planet: Entity,
+    pub position_degrees: f32,
+}
+
+struct LogEntry {
+    event: ColonyEvent,
+    age: f32,
+}
+
+#[derive(Resource, Default)]
+struct NotificationLog {
+    entries: VecDeque<LogEntry>,
+}
+
+#[derive(Component)]
+struct NotificationPanel;
+
+#[derive(Component)]
+struct NotificationEntry {
+    event: ColonyEvent,
+}
+
+pub struct NotificationsPlugin;
+
+impl Plugin for NotificationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NotificationLog::default())
+            .add_event::<ColonyEvent>()
+            .add_systems(Startup, spawn_notification_panel)
+            .add_systems(Update, (record_colony_events, expire_notifications, rebuild_notification_panel, handle_notification_clicks).chain());
+    }
+}
+
+fn spawn_notification_panel(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(5.),
+            bottom: Val::Px(5.),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.),
+            ..default()
+        },
+        NotificationPanel,
+        Name::new("NotificationPanel"),
+    ));
+}
+
+fn record_colony_events(
+    mut ev_colony: EventReader<ColonyEvent>,
+    mut log: ResMut<NotificationLog>,
+) {
+    for ev in ev_colony.read() {
+        log.entries.push_back(LogEntry { event: *ev, age: 0. });
+        if log.entries.len() > MAX_ENTRIES {
+            log.entries.pop_front();
+        }
+    }
+}
+
+fn expire_notifications(
+    mut log: ResMut<NotificationLog>,
+    time: Res<Time>,
+) {
+    // Ageing alone doesn't need the panel rebuilt, only dropping an entry does.
+    for entry in log.bypass_change_detection().entries.iter_mut() {
+        entry.age += time.delta_secs();
+    }
+    if log.entries.iter().any(|entry| entry.age >= ENTRY_LIFETIME) {
+        log.entries.retain(|entry| entry.age < ENTRY_LIFETIME);
+    }
+}
+
+fn rebuild_notification_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    log: Res<NotificationLog>,
+    panel_query: Query<Entity, With<NotificationPanel>>,
+) {
+    if !log.is_changed() { return; }
+    let Ok(panel) = panel_query.get_single() else { return; };
+    commands.entity(panel).despawn_descendants();
+    let font = TextFont {
+        font: asset_server.load("fonts/pixel.ttf"),
+        font_size: 16.0,
+        ..default()
+    };
+    commands.entity(panel).with_children(|parent| {
+        for entry in log.entries.iter() {
+            parent.spawn((
+                Button,
+                Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(6.),
+                    padding: UiRect::axes(Val::Px(6.), Val::Px(2.)),
+                    border: UiRect::all(Val::Px(1.)),
+                    ..default()
+                },
+                BorderColor(Color::WHITE),
+                BackgroundColor(Color::BLACK),
+                NotificationEntry { event: entry.event },
+            )).with_children(|button| {
+                button.spawn((
+                    ImageNode::new(asset_server.load(entry.event.kind.icon_path())),
+                    Node {
+                        width: Val::Px(16.),
+                        height: Val::Px(16.),
+                        ..default()
+                    },
+                ));
+                button.spawn((Text::new(entry.event.kind.message()), font.clone()));
+            });
+        }
+    });
+}
+
+fn handle_notification_clicks(
+    buttons_query: Query<(&Interaction, &NotificationEntry), Changed<Interaction>>,
+    mut camera_controller: ResMut<CameraController>,
+) {
+    for (interaction, entry) in buttons_query.iter() {
+        if *interaction != Interaction::Pressed { continue; }
+        camera_controller.mode = CameraMode::Surface {
+            planet: entry.event.planet,
+            position_degrees: entry.event.position_degrees,
+        };
+    }
+}
@@ -1,12 +1,17 @@
-use bevy::{prelude::*, sprite::Anchor};
+use bevy::{prelude::*, sprite::Anchor, utils::HashMap};
 
 use crate::{
     button_value,
+    bridge::{reachable_shore, spawn_bridge},
+    housing::spawn_house,
+    processing::spawn_processor,
+    storage::spawn_storage,
     looping_float::LoopingFloat,
     natural_resource::NaturalResource,
+    pathfinding::SurfaceGraphs,
     occupable_counter::{self, OccupableCounter},
-    planet::Planet,
-    planet_placing::{BuildingType, GetBuildingInfo},
+    planet::{Planet, PlanetWater},
+    planet_placing::{Building, BuildingType, GetBuildingInfo},
     planet_sticker::{self, PlanetSticker},
     planet_villager::*,
     scaling_sprite::ScalingSprite,
@@ -17,12 +22,41 @@ pub struct SelectedOccupable {
     pub occupable: Option<Entity>,
 }
 
-#[derive(PartialEq)]
+#[derive(Event)]
+pub struct SelectionChanged {
+    pub current: Option<Entity>,
+}
+
+// The only place the selection gets written, so every change is announced.
+pub fn set_selection(
+    selected_occupable: &mut ResMut<SelectedOccupable>,
+    ev_selection: &mut EventWriter<SelectionChanged>,
+    occupable: Option<Entity>,
+) {
+    if selected_occupable.occupable == occupable { return; }
+    ev_selection.send(SelectionChanged { current: occupable });
+    selected_occupable.occupable = occupable;
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum OccupableType {
     Cutting,
     Foraging,
     Fishing,
+    Mining,
     Interior,
+    Construction,
+}
+
+impl OccupableType {
+    pub const ALL: [OccupableType; 6] = [
+        OccupableType::Cutting,
+        OccupableType::Foraging,
+        OccupableType::Fishing,
+        OccupableType::Mining,
+        OccupableType::Interior,
+        OccupableType::Construction,
+    ];
 }
 
 #[derive(Event)]
@@ -33,21 +67,65 @@ pub struct OccupancyChange {
 
 #[derive(Component, PartialEq)]
 pub struct Occupable {
-    pub selected: bool,
     pub max_workers: u32,
     pub occupable_type: OccupableType,
 }
 
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum HarvestPreference {
+    Nearest,
+    MostAbundant,
+}
+
 #[derive(Component, PartialEq)]
 pub struct Automator {
-    pub exploited_resource: ResourceType,
+    pub exploited_resources: Vec<ResourceType>,
+    pub worked_occupables: Vec<OccupableType>,
     pub range: f32,
+    pub harvested_resources: Vec<ResourceType>,
+    pub reserve: u32,
+    pub preference: HarvestPreference,
+}
+
+impl Automator {
+    pub fn new(exploited_resources: Vec<ResourceType>, worked_occupables: Vec<OccupableType>, range: f32) -> Automator {
+        Automator {
+            harvested_resources: exploited_resources.clone(),
+            exploited_resources,
+            worked_occupables,
+            range,
+            reserve: 0,
+            preference: HarvestPreference::Nearest,
+        }
+    }
+
+    // Whether this building's workers may keep taking from the given node.
+    pub fn allows(&self, natural_resource: &NaturalResource, occupable: &Occupable) -> bool {
+        return self.worked_occupables.contains(&occupable.occupable_type)
+            && self.harvested_resources.contains(&natural_resource.produced_resource)
+            && natural_resource.amount_remaining > self.reserve;
+    }
 }
 
-#[derive(PartialEq, Eq, Copy, Clone)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum ResourceType {
     Food,
     Wood,
+    Planks,
+    Meals,
+    Stone,
+    IronOre,
+}
+
+impl ResourceType {
+    pub const ALL: [ResourceType; 6] = [
+        ResourceType::Food,
+        ResourceType::Wood,
+        ResourceType::Planks,
+        ResourceType::Meals,
+        ResourceType::Stone,
+        ResourceType::IronOre,
+    ];
 }
 
 pub struct OccupableParameters {
@@ -78,22 +156,27 @@ pub struct OccupablePlugin;
 
 impl Plugin for OccupablePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, select_entity_system)
+        app.add_systems(Update, (select_entity_system, deselect_on_escape, cycle_selection, deselect_missing).chain())
             .add_systems(Update, find_and_assign_villagers)
             .add_systems(Update, spawn_ui)
             .add_systems(Update, handle_automators)
             .insert_resource(SelectedOccupable::default())
+            .add_event::<SelectionChanged>()
             .add_event::<OccupancyChange>();
     }
 }
 
 fn spawn_ui(
-    q: Query<Entity, (With<Occupable>, Without<Children>)>,
+    q: Query<(Entity, Option<&Children>), With<Occupable>>,
+    counters_query: Query<(), With<OccupableCounter>>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
-    for e in q.iter() {
+    for (e, children) in q.iter() {
+        if children.is_some_and(|children| children.iter().any(|child| counters_query.contains(*child))) {
+            continue;
+        }
         let minus = spawn_button(
             &mut commands,
             &asset_server,
@@ -223,25 +306,33 @@ fn find_and_assign_villagers(
     mut wandering_query: Query<(Entity, &PlanetSticker), With<VillagerWandering>>,
     mut working_query: Query<(Entity, &VillagerWorking)>,
     mut occupable_query: Query<(Entity, &PlanetSticker)>,
+    waters_query: Query<&PlanetSticker, With<PlanetWater>>,
+    graphs: Res<SurfaceGraphs>,
     mut commands: Commands,
 ) {
+    let mut assigned: Vec<Entity> = vec![];
     for ev in ev_occupancy.read() {
         if ev.change == 1 {
+            let Ok((_, occupable_sticker)) = occupable_query.get(ev.occupable) else { continue; };
+            // Hand the job to whoever would get there first, rather than whoever happens to come first in the query.
+            let mut fastest: Option<(Entity, f32)> = None;
             for (villager_entity, sticker) in wandering_query.iter_mut() {
-                if let Ok((_, occupable_sticker)) = occupable_query.get(ev.occupable) {
-                    if sticker.planet == occupable_sticker.planet {
-                        commands
-                            .entity(villager_entity)
-                            .remove::<VillagerWandering>()
-                            .insert(VillagerWorking {
-                                current_occupable: ev.occupable,
-                                current_work: ev.occupable,
-                                production_interval: 1.0,
-                            });
-                        return;
-                    }
-                }
+                if sticker.planet != occupable_sticker.planet { continue; }
+                if assigned.contains(&villager_entity) { continue; }
+                // Bridge sites over water are worked from the shore, so that is where the walk ends.
+                let destination = reachable_shore(sticker, occupable_sticker, waters_query.iter(), &graphs)
+                    .unwrap_or(occupable_sticker.position_degrees);
+                let Some(path) = graphs.find_path(sticker, destination) else { continue; };
+                let eta = path.eta(WORKER_SPEED);
+                if fastest.is_some_and(|(_, best_eta)| best_eta <= eta) { continue; }
+                fastest = Some((villager_entity, eta));
             }
+            let Some((villager_entity, _)) = fastest else { continue; };
+            assigned.push(villager_entity);
+            commands
+                .entity(villager_entity)
+                .remove::<VillagerWandering>()
+                .insert(VillagerWorking::new(ev.occupable));
         } else if ev.change == -1 {
             if let Ok((occupable_entity, _)) = occupable_query.get_mut(ev.occupable) {
                 for (worker_entity, worker) in working_query.iter_mut() {
@@ -252,54 +343,59 @@ fn find_and_assign_villagers(
                         .entity(worker_entity)
                         .remove::<VillagerWorking>()
                         .insert(VillagerWandering::default());
-                    return;
+                    break;
                 }
             }
         }
     }
 }
 
+// Whether an automator can reach a resource, kept until the surface graphs are rebuilt.
+#[derive(Default)]
+struct ReachableCache {
+    generation: u32,
+    reachable: HashMap<(Entity, Entity), bool>,
+}
+
 fn handle_automators(
     planets_query: Query<&Planet>,
-    automator_query: Query<(Entity, &Automator, &Occupable, &PlanetSticker)>,
-    mut natural_resource_query: Query<
-        (Entity, &NaturalResource, &mut Occupable, &PlanetSticker),
+    automator_query: Query<(Entity, &Automator, &PlanetSticker)>,
+    natural_resource_query: Query<
+        (Entity, &NaturalResource, &Occupable, &PlanetSticker),
         Without<Automator>,
     >,
     mut villager_query: Query<(Entity, &mut VillagerWorking)>,
+    graphs: Res<SurfaceGraphs>,
+    mut reachable_cache: Local<ReachableCache>,
 ) {
-    for (automator_entity, automator, _automator_occupable, automator_sticker) in
-        automator_query.iter()
-    {
+    if reachable_cache.generation != graphs.generation() {
+        reachable_cache.generation = graphs.generation();
+        reachable_cache.reachable.clear();
+    }
+    for (automator_entity, automator, automator_sticker) in automator_query.iter() {
         let mut free: Vec<Entity> = vec![];
         for (villager_entity, villager) in villager_query.iter() {
             if villager.current_work == automator_entity {
                 free.push(villager_entity);
             }
         }
+        if free.is_empty() {
+            continue;
+        }
+        let Some(planet_entity) = automator_sticker.planet else {
+            continue;
+        };
+        let Ok(planet) = planets_query.get(planet_entity) else {
+            continue;
+        };
+        let mut candidates: Vec<(Entity, f32, u32, u32)> = vec![];
         for (occupable_entity, natural_resource, occupable, occupable_sticker) in
-            natural_resource_query.iter_mut()
+            natural_resource_query.iter()
         {
-            if free.is_empty() {
-                continue;
-            }
             if automator_entity == occupable_entity {
                 continue;
             }
-            let mut count = 0;
-
-            for (_, villager) in villager_query.iter() {
-                if villager.current_work == occupable_entity {
-                    count += 1;
-                }
-            }
-            if count == occupable.max_workers {
-                continue;
-            };
-            let Some(planet_entity) = automator_sticker.planet else {
-                continue;
-            };
-            let Ok(planet) = planets_query.get(planet_entity) else {
+            if !automator.allows(natural_resource, occupable) {
                 continue;
             };
             let dist: f32 = automator_sticker
@@ -308,33 +404,137 @@ fn handle_automators(
             if dist > automator.range {
                 continue;
             }
-            if automator.exploited_resource != natural_resource.produced_resource {
-                continue;
-            };
-            let Some(villager_entity) = free.last() else {
+            // Resources cut off by water are in range but can't be worked.
+            let reachable = *reachable_cache.reachable
+                .entry((automator_entity, occupable_entity))
+                .or_insert_with(|| graphs.find_path(automator_sticker, occupable_sticker.position_degrees).is_some());
+            if !reachable {
                 continue;
-            };
-            let Ok((_, mut villager)) = villager_query.get_mut(*villager_entity) else {
+            }
+            let mut count = 0;
+            for (_, villager) in villager_query.iter() {
+                if villager.current_work == occupable_entity {
+                    count += 1;
+                }
+            }
+            if count >= occupable.max_workers {
                 continue;
             };
-            villager.current_work = occupable_entity;
-            free.pop();
+            candidates.push((occupable_entity, dist, natural_resource.amount_remaining, occupable.max_workers - count));
+        }
+        match automator.preference {
+            HarvestPreference::Nearest => candidates.sort_by(|a, b| a.1.total_cmp(&b.1)),
+            HarvestPreference::MostAbundant => candidates.sort_by(|a, b| b.2.cmp(&a.2).then(a.1.total_cmp(&b.1))),
+        }
+        for (occupable_entity, _, _, open_slots) in candidates {
+            for _ in 0..open_slots {
+                let Some(villager_entity) = free.pop() else {
+                    break;
+                };
+                let Ok((_, mut villager)) = villager_query.get_mut(villager_entity) else {
+                    continue;
+                };
+                villager.current_work = occupable_entity;
+            }
         }
     }
 }
 
 fn select_entity_system(
+    mut press_events: EventReader<Pointer<Down>>,
     mut events: EventReader<Pointer<Click>>,
+    keys: Res<ButtonInput<KeyCode>>,
     mut selected_occuppable: ResMut<SelectedOccupable>,
+    mut ev_selection: EventWriter<SelectionChanged>,
     query: Query<Entity, With<Occupable>>,
+    windows_query: Query<(), With<Window>>,
+    mut press_position: Local<Option<Vec2>>,
 ) {
+    for event in press_events.read() {
+        if windows_query.contains(event.target) {
+            *press_position = Some(event.pointer_location.position);
+        }
+    }
     for event in events.read() {
+        // Shift-clicks build up a group instead, see `group_selection`.
+        if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) { continue; }
         if query.get(event.target).is_ok() {
-            selected_occuppable.occupable = Some(event.target);
+            set_selection(&mut selected_occuppable, &mut ev_selection, Some(event.target));
+        } else if windows_query.contains(event.target) {
+            // Nothing but the window was hit, unless the camera was being dragged around.
+            let dragged = press_position.is_some_and(|pressed| pressed.distance(event.pointer_location.position) > 4.);
+            if !dragged {
+                set_selection(&mut selected_occuppable, &mut ev_selection, None);
+            }
         }
     }
 }
 
+fn deselect_on_escape(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut selected_occupable: ResMut<SelectedOccupable>,
+    mut ev_selection: EventWriter<SelectionChanged>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        set_selection(&mut selected_occupable, &mut ev_selection, None);
+    }
+}
+
+fn cycle_selection(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut selected_occupable: ResMut<SelectedOccupable>,
+    mut ev_selection: EventWriter<SelectionChanged>,
+    occupables_query: Query<(Entity, &PlanetSticker), With<Occupable>>,
+    planets_query: Query<(Entity, &Planet, &GlobalTransform)>,
+    camera_query: Query<&GlobalTransform, With<Camera>>,
+) {
+    if !keys.just_pressed(KeyCode::Tab) { return; }
+    let backwards = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+    let selected_planet = selected_occupable.occupable
+        .and_then(|selected| occupables_query.get(selected).ok())
+        .and_then(|(_, sticker)| sticker.planet);
+    let Some(planet) = selected_planet.or_else(|| closest_planet_to_camera(&planets_query, &camera_query)) else { return; };
+    let mut occupables: Vec<(Entity, f32)> = occupables_query.iter()
+        .filter(|(_, sticker)| sticker.planet == Some(planet))
+        .map(|(entity, sticker)| (entity, sticker.position_degrees.to_f32()))
+        .collect();
+    if occupables.is_empty() { return; }
+    occupables.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let current = selected_occupable.occupable.and_then(|selected| occupables.iter().position(|(entity, _)| *entity == selected));
+    let next = match (current, backwards) {
+        (None, false) => 0,
+        (None, true) => occupables.len() - 1,
+        (Some(index), false) => (index + 1) % occupables.len(),
+        (Some(index), true) => (index + occupables.len() - 1) % occupables.len(),
+    };
+    set_selection(&mut selected_occupable, &mut ev_selection, Some(occupables[next].0));
+}
+
+fn closest_planet_to_camera(
+    planets_query: &Query<(Entity, &Planet, &GlobalTransform)>,
+    camera_query: &Query<&GlobalTransform, With<Camera>>,
+) -> Option<Entity> {
+    let camera_position = camera_query.get_single().ok()?.translation().xy();
+    let mut closest: Option<(Entity, f32)> = None;
+    for (planet_entity, planet, planet_transform) in planets_query.iter() {
+        let dist = camera_position.distance(planet_transform.translation().xy()) - planet.radius;
+        if closest.is_some_and(|(_, best)| best <= dist) { continue; }
+        closest = Some((planet_entity, dist));
+    }
+    return closest.map(|(planet_entity, _)| planet_entity);
+}
+
+fn deselect_missing(
+    mut selected_occupable: ResMut<SelectedOccupable>,
+    mut ev_selection: EventWriter<SelectionChanged>,
+    occupables_query: Query<(), With<Occupable>>,
+) {
+    let Some(selected) = selected_occupable.occupable else { return; };
+    if !occupables_query.contains(selected) {
+        set_selection(&mut selected_occupable, &mut ev_selection, None);
+    }
+}
+
 pub fn spawn_occupable(commands: &mut Commands, occupable: OccupableParameters) -> Entity {
     let created = commands.spawn((
         Sprite {
@@ -353,7 +553,6 @@ pub fn spawn_occupable(commands: &mut Commands, occupable: OccupableParameters)
         },
         Occupable {
             occupable_type: occupable.occupable_type,
-            selected: false,
             max_workers: occupable.max_workers,
         },
         ScalingSprite {
@@ -361,9 +560,6 @@ pub fn spawn_occupable(commands: &mut Commands, occupable: OccupableParameters)
         },
         Name::new("Occupable"),
     )).id();
-    commands.entity(created).observe(|trigger: Trigger<Pointer<Click>>, mut occupables: Query<&mut Occupable> | 
-        occupables.get_mut(trigger.entity()).unwrap().selected = true
-    );
     created
 }
 
@@ -371,13 +567,12 @@ fn spawn_automator(
     commands: &mut Commands,
     occupable_parameters: OccupableParameters,
     range: f32,
-    exploited_resource: ResourceType,
-) {
+    exploited_resources: Vec<ResourceType>,
+    worked_occupables: Vec<OccupableType>,
+) -> Entity {
     let occupable = spawn_occupable(commands, occupable_parameters);
-    commands.entity(occupable).insert(Automator {
-        exploited_resource,
-        range,
-    });
+    commands.entity(occupable).insert(Automator::new(exploited_resources, worked_occupables, range));
+    return occupable;
 }
 
 pub fn spawn_building(
@@ -386,23 +581,61 @@ pub fn spawn_building(
     planet: Entity,
     position_degrees: f32,
     building_type: BuildingType,
-) {
-    let texture_path = match building_type {
-        BuildingType::Sawmill => "buildings/sawmill.png",
-    };
+) -> Entity {
     let info = building_type.get_building_info();
-    spawn_automator(
-        commands,
-        OccupableParameters::new(
-            asset_server.load(texture_path),
-            planet,
-            position_degrees,
-            OccupableType::Interior,
-            3,
-            16.,
-            Anchor::BottomCenter,
-        ),
-        info.range,
-        info.exploited_resource,
+    let texture = asset_server.load(info.texture_path);
+    let occupable_parameters = OccupableParameters::new(
+        texture.clone(),
+        planet,
+        position_degrees,
+        OccupableType::Interior,
+        info.max_workers,
+        16.,
+        Anchor::BottomCenter,
     );
+    let building = match building_type {
+        BuildingType::House => spawn_house(commands, texture, planet, position_degrees, info.beds),
+        BuildingType::Storage => spawn_storage(commands, texture, planet, position_degrees),
+        BuildingType::Bridge => spawn_bridge(commands, texture, planet, position_degrees),
+        BuildingType::Carpenter | BuildingType::Kitchen => spawn_processor(commands, occupable_parameters, info.recipe.unwrap()),
+        BuildingType::Sawmill | BuildingType::Quarry | BuildingType::Port => spawn_automator(commands, occupable_parameters, info.range, info.exploited_resources.to_vec(), info.worked_occupables.to_vec()),
+    };
+    commands.entity(building).insert(Building { building_type, level: 1 });
+    return building;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinding::PathfindingPlugin;
+
+    fn sticker(planet: Entity, position_degrees: f32, size_degrees: Option<f32>) -> PlanetSticker {
+        return PlanetSticker { planet: Some(planet), position_degrees: LoopingFloat::new(position_degrees), size_degrees };
+    }
+
+    #[test]
+    fn site_in_unbridged_water_still_gets_a_builder() {
+        let mut app = App::new();
+        app.add_plugins(PathfindingPlugin)
+            .add_event::<OccupancyChange>()
+            .add_systems(Update, find_and_assign_villagers);
+        let planet = app.world_mut().spawn(Planet { radius: 100., gravity: 1., atmosphere: 1. }).id();
+        app.world_mut().spawn((PlanetWater {}, sticker(planet, 90., Some(10.))));
+        // Placed right in the middle of the water, where nobody can walk to.
+        let site = app.world_mut().spawn(sticker(planet, 90., Some(10.))).id();
+        let villager = app.world_mut().spawn((VillagerWandering::default(), sticker(planet, 60., None))).id();
+        app.world_mut().send_event(OccupancyChange { occupable: site, change: 1 });
+        app.update();
+        let worker = app.world().get::<VillagerWorking>(villager);
+        assert!(worker.is_some_and(|worker| worker.current_occupable == site));
+    }
+
+    #[test]
+    fn port_fishes_but_leaves_berries_alone() {
+        let info = BuildingType::Port.get_building_info();
+        let port = Automator::new(info.exploited_resources.to_vec(), info.worked_occupables.to_vec(), info.range);
+        let food = NaturalResource { produced_resource: ResourceType::Food, amount_remaining: 10 };
+        assert!(port.allows(&food, &Occupable { max_workers: 1, occupable_type: OccupableType::Fishing }));
+        assert!(!port.allows(&food, &Occupable { max_workers: 1, occupable_type: OccupableType::Foraging }));
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{occupables::*, planet::PlanetWater, planet_sticker::{self, PlanetSticker}, planet_villager::{self, count_occupiers, count_workers, VillagerWandering, VillagerWorking}, Occupable};
+use crate::{bridge::reachable_shore, occupables::*, pathfinding::SurfaceGraphs, planet::PlanetWater, planet_sticker::{self, PlanetSticker}, planet_villager::{self, count_occupiers, count_workers, VillagerWandering, VillagerWorking}};
 use bevy::prelude::*;
 use occupable::OccupancyChange;
 
@@ -62,7 +62,8 @@ fn handle_counters(
     selected_occupable: Res<occupable::SelectedOccupable>,
     wandering_query: Query<&PlanetSticker, With<VillagerWandering>>,
     working_query: Query<&VillagerWorking>,
-    water_query: Query<&PlanetSticker, (With<PlanetWater>, Without<Occupable>, Without<VillagerWorking>, Without<VillagerWandering>)>,
+    waters_query: Query<&PlanetSticker, With<PlanetWater>>,
+    graphs: Res<SurfaceGraphs>,
 ) {
     for (parent, counter, visibility) in counters_query.iter_mut() {
         if let Ok((occupable_entity, occupable, occupable_sticker)) = occupables_query.get(parent.get()) {
@@ -81,7 +82,9 @@ fn handle_counters(
                 let mut found = false;
                 for villager_sticker in wandering_query.iter() {
                     if villager_sticker.planet == occupable_sticker.planet {
-                        if planet_villager::get_walk_dir(&villager_sticker, &water_query, occupable_sticker.position_degrees).is_some() {
+                        let destination = reachable_shore(villager_sticker, occupable_sticker, waters_query.iter(), &graphs)
+                            .unwrap_or(occupable_sticker.position_degrees);
+                        if planet_villager::get_walk_dir(&villager_sticker, &graphs, destination).is_some() {
                             found = true;
                         }
                     }   
@@ -0,0 +1,360 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    bridge::{is_bridged, Bridge},
+    elevation::PlanetElevation,
+    looping_float::LoopingFloat,
+    planet::{Planet, PlanetWater},
+    planet_placing::{Building, BuildingType},
+    planet_sticker::PlanetSticker,
+};
+
+// World units of boggy ground on either side of a water.
+const SWAMP_WIDTH: f32 = 28.;
+const SWAMP_COST: f32 = 1.5;
+const FERRY_COST: f32 = 2.;
+// World units along the surface a port may stand from the shore it serves.
+const PORT_REACH: f32 = 18.;
+// Degrees of flat ground that climbing a degree's length of height is worth.
+const SLOPE_COST: f32 = 3.;
+
+struct TerrainFeature {
+    center: LoopingFloat<360>,
+    half_size: f32,
+    cost: f32,
+}
+
+// A planet's surface split at every feature edge, so the ground between two boundaries has a single cost.
+pub struct SurfaceGraph {
+    boundaries: Vec<f32>,
+    features: Vec<TerrainFeature>,
+    elevation: Option<PlanetElevation>,
+    // World units in one degree of surface.
+    degree_length: f32,
+    gravity: f32,
+}
+
+impl Default for SurfaceGraph {
+    // Without a planet to measure, a degree counts as a world unit.
+    fn default() -> SurfaceGraph {
+        SurfaceGraph {
+            boundaries: vec![],
+            features: vec![],
+            elevation: None,
+            degree_length: 1.,
+            gravity: 1.,
+        }
+    }
+}
+
+pub struct SurfacePath {
+    pub direction: i32,
+    pub waypoints: Vec<LoopingFloat<360>>,
+    pub cost: f32,
+    // Degrees per second covered for each world unit per second of speed.
+    pace: f32,
+}
+
+impl SurfacePath {
+    // Seconds needed to walk the path at `speed` world units per second.
+    pub fn eta(&self, speed: f32) -> f32 {
+        return self.cost / (speed * self.pace);
+    }
+}
+
+impl SurfaceGraph {
+    fn add_feature(&mut self, center: LoopingFloat<360>, half_size: f32, cost: f32) {
+        self.boundaries.push((center - half_size).to_f32());
+        self.boundaries.push((center + half_size).to_f32());
+        self.features.push(TerrainFeature { center, half_size, cost });
+    }
+
+    // Walking is slower under a heavier pull, by the square root so low gravity doesn't turn villagers into rockets.
+    fn pace(&self) -> f32 {
+        return 1. / (self.degree_length * self.gravity.max(0.01).sqrt());
+    }
+
+    pub fn cost_at(&self, position_degrees: f32) -> f32 {
+        let mut cost: f32 = 1.;
+        for feature in self.features.iter() {
+            if feature.center.distance(position_degrees) <= feature.half_size {
+                cost = cost.max(feature.cost);
+            }
+        }
+        return cost;
+    }
+
+    // How steeply the ground climbs when heading in `direction`, zero when it goes down.
+    fn uphill_slope(&self, position_degrees: f32, direction: i32) -> f32 {
+        let Some(elevation) = &self.elevation else { return 0.; };
+        let sign = direction as f32;
+        let rise = elevation.height_at(position_degrees + sign * 0.5) - elevation.height_at(position_degrees - sign * 0.5);
+        return (rise / self.degree_length).max(0.);
+    }
+
+    // Height climbed over `distance` degrees from `start`, measured in degrees of surface.
+    fn climb(&self, start: LoopingFloat<360>, distance: f32, sign: f32) -> f32 {
+        let Some(elevation) = &self.elevation else { return 0.; };
+        let steps = distance.ceil().max(1.);
+        let mut climbed = 0.;
+        let mut previous = elevation.height_at(start.to_f32());
+        for step in 1..=(steps as u32) {
+            let height = elevation.height_at((start + distance * step as f32 / steps * sign).to_f32());
+            climbed += (height - previous).max(0.);
+            previous = height;
+        }
+        return climbed / self.degree_length;
+    }
+
+    pub fn find_path(&self, start: LoopingFloat<360>, end: LoopingFloat<360>) -> Option<SurfacePath> {
+        let clockwise = self.route(start, end, 1);
+        let counter_clockwise = self.route(start, end, -1);
+        match (clockwise, counter_clockwise) {
+            (Some(clockwise), Some(counter_clockwise)) => {
+                if clockwise.cost <= counter_clockwise.cost {
+                    return Some(clockwise);
+                }
+                return Some(counter_clockwise);
+            }
+            (clockwise, counter_clockwise) => return clockwise.or(counter_clockwise),
+        }
+    }
+
+    fn route(&self, start: LoopingFloat<360>, end: LoopingFloat<360>, direction: i32) -> Option<SurfacePath> {
+        let sign = direction as f32;
+        // Everything is measured as distance travelled from the start, which sidesteps the 0°/360° seam.
+        let length = LoopingFloat::<360>::new((end.to_f32() - start.to_f32()) * sign).to_f32();
+        let mut stops: Vec<f32> = self.boundaries.iter()
+            .map(|boundary| LoopingFloat::<360>::new((boundary - start.to_f32()) * sign).to_f32())
+            .filter(|travelled| *travelled > 0. && *travelled < length)
+            .collect();
+        stops.sort_by(|a, b| a.total_cmp(b));
+        stops.push(length);
+        let mut travelled = 0.;
+        let mut cost = 0.;
+        let mut waypoints = vec![];
+        for stop in stops {
+            let midpoint = start + (travelled + stop) / 2. * sign;
+            let mut segment_cost = self.cost_at(midpoint.to_f32());
+            if segment_cost.is_infinite() {
+                // Whatever a villager is standing on, it can always step off of it.
+                if travelled > 0. { return None; }
+                segment_cost = 1.;
+            }
+            let climbed = self.climb(start + travelled * sign, stop - travelled, sign);
+            cost += (stop - travelled + climbed * SLOPE_COST) * segment_cost;
+            travelled = stop;
+            waypoints.push(start + stop * sign);
+        }
+        return Some(SurfacePath { direction, waypoints, cost, pace: self.pace() });
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SurfaceGraphs {
+    graphs: HashMap<Entity, SurfaceGraph>,
+    // Goes up on every rebuild, so paths found on an older graph can tell they are stale.
+    generation: u32,
+}
+
+// The path a villager is walking, kept until the graphs are rebuilt, the destination changes
+// or something other than the walk itself moves the villager.
+#[derive(Component, Default)]
+pub struct CachedPath {
+    generation: u32,
+    // Planet, position and destination the path was found for.
+    key: Option<(Option<Entity>, f32, f32)>,
+    path: Option<SurfacePath>,
+}
+
+impl CachedPath {
+    // Keeps the path valid after the walker took a step along it.
+    pub fn moved_to(&mut self, position_degrees: f32) {
+        if let Some(key) = self.key.as_mut() {
+            key.1 = position_degrees;
+        }
+    }
+}
+
+impl SurfaceGraphs {
+    pub fn find_path(&self, sticker: &PlanetSticker, destination: LoopingFloat<360>) -> Option<SurfacePath> {
+        let planet = sticker.planet?;
+        match self.graphs.get(&planet) {
+            Some(graph) => return graph.find_path(sticker.position_degrees, destination),
+            None => return SurfaceGraph::default().find_path(sticker.position_degrees, destination),
+        }
+    }
+
+    pub fn generation(&self) -> u32 {
+        return self.generation;
+    }
+
+    pub fn cached_path<'a>(&self, sticker: &PlanetSticker, destination: LoopingFloat<360>, cached: &'a mut CachedPath) -> Option<&'a mut SurfacePath> {
+        let key = (sticker.planet, sticker.position_degrees.to_f32(), destination.to_f32());
+        if cached.generation != self.generation || cached.key != Some(key) {
+            cached.generation = self.generation;
+            cached.key = Some(key);
+            cached.path = self.find_path(sticker, destination);
+        }
+        return cached.path.as_mut();
+    }
+
+    // How many degrees `world_units` of surface span on the sticker's planet.
+    pub fn degrees_for(&self, sticker: &PlanetSticker, world_units: f32) -> f32 {
+        let degree_length = sticker.planet.and_then(|planet| self.graphs.get(&planet)).map_or(1., |graph| graph.degree_length);
+        return world_units / degree_length;
+    }
+
+    pub fn degrees_per_second(&self, sticker: &PlanetSticker, speed: f32) -> f32 {
+        let pace = sticker.planet.and_then(|planet| self.graphs.get(&planet)).map_or(1., |graph| graph.pace());
+        return speed * pace;
+    }
+
+    // How much of its normal speed a villager heading in `direction` keeps at this spot, matching what paths cost.
+    pub fn speed_factor(&self, sticker: &PlanetSticker, direction: i32) -> f32 {
+        let Some(graph) = sticker.planet.and_then(|planet| self.graphs.get(&planet)) else { return 1.; };
+        let position = sticker.position_degrees.to_f32();
+        let mut cost = graph.cost_at(position);
+        if cost.is_infinite() { cost = 1.; }
+        return 1. / (cost * (1. + graph.uphill_slope(position, direction) * SLOPE_COST));
+    }
+}
+
+pub struct PathfindingPlugin;
+
+impl Plugin for PathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SurfaceGraphs::default())
+            .add_systems(PreUpdate, rebuild_surface_graphs);
+    }
+}
+
+fn rebuild_surface_graphs(
+    mut graphs: ResMut<SurfaceGraphs>,
+    planets_query: Query<(Entity, &Planet, Option<&PlanetElevation>)>,
+    waters_query: Query<&PlanetSticker, With<PlanetWater>>,
+    buildings_query: Query<(&Building, &PlanetSticker)>,
+    bridges_query: Query<&PlanetSticker, With<Bridge>>,
+    changed_query: Query<(), (Or<(With<PlanetWater>, With<Building>)>, Changed<PlanetSticker>)>,
+    changed_elevations: Query<(), Changed<PlanetElevation>>,
+    mut removed_waters: RemovedComponents<PlanetWater>,
+    mut removed_buildings: RemovedComponents<Building>,
+) {
+    let removed = removed_waters.read().count() + removed_buildings.read().count() > 0;
+    if !graphs.is_added() && changed_query.is_empty() && changed_elevations.is_empty() && !removed { return; }
+    graphs.graphs.clear();
+    graphs.generation = graphs.generation.wrapping_add(1);
+    for (planet, planet_info, elevation) in planets_query.iter() {
+        let ports: Vec<LoopingFloat<360>> = buildings_query.iter()
+            .filter(|(building, sticker)| building.building_type == BuildingType::Port && sticker.planet == Some(planet))
+            .map(|(_, sticker)| sticker.position_degrees)
+            .collect();
+        let degree_length = planet_info.radius * 1f32.to_radians();
+        let port_reach = PORT_REACH / degree_length;
+        let swamp_width = SWAMP_WIDTH / degree_length;
+        let has_port = |edge: LoopingFloat<360>| ports.iter().any(|port| port.distance(edge.to_f32()) <= port_reach);
+        let mut graph = SurfaceGraph {
+            elevation: elevation.cloned(),
+            degree_length,
+            gravity: planet_info.gravity,
+            ..default()
+        };
+        for water in waters_query.iter() {
+            if water.planet != Some(planet) { continue; }
+            let Some(size) = water.size_degrees else { continue; };
+            let start_edge = water.position_degrees - size / 2.;
+            let end_edge = water.position_degrees + size / 2.;
+            // Boats only run between two ports, one on each shore.
+            let water_cost = if is_bridged(water, bridges_query.iter()) {
+                1.
+            } else if has_port(start_edge) && has_port(end_edge) {
+                FERRY_COST
+            } else {
+                f32::INFINITY
+            };
+            graph.add_feature(water.position_degrees, size / 2., water_cost);
+            graph.add_feature(start_edge - swamp_width / 2., swamp_width / 2., SWAMP_COST);
+            graph.add_feature(end_edge + swamp_width / 2., swamp_width / 2., SWAMP_COST);
+        }
+        graphs.graphs.insert(planet, graph);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn degrees(value: f32) -> LoopingFloat<360> {
+        return LoopingFloat::new(value);
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.001, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn crosses_zero_on_open_ground() {
+        let graph = SurfaceGraph::default();
+        let path = graph.find_path(degrees(350.), degrees(10.)).unwrap();
+        assert_eq!(path.direction, 1);
+        assert_close(path.cost, 20.);
+        let back = graph.find_path(degrees(10.), degrees(350.)).unwrap();
+        assert_eq!(back.direction, -1);
+        assert_close(back.cost, 20.);
+    }
+
+    #[test]
+    fn goes_around_water_on_the_seam() {
+        let mut graph = SurfaceGraph::default();
+        graph.add_feature(degrees(0.), 10., f32::INFINITY);
+        let path = graph.find_path(degrees(340.), degrees(20.)).unwrap();
+        assert_eq!(path.direction, -1);
+        assert_close(path.cost, 320.);
+    }
+
+    #[test]
+    fn waypoints_stop_at_every_edge_across_the_seam() {
+        let mut graph = SurfaceGraph::default();
+        graph.add_feature(degrees(0.), 5., FERRY_COST);
+        let path = graph.find_path(degrees(340.), degrees(20.)).unwrap();
+        let waypoints: Vec<f32> = path.waypoints.iter().map(|waypoint| waypoint.to_f32()).collect();
+        assert_eq!(waypoints.len(), 3);
+        assert_close(waypoints[0], 355.);
+        assert_close(waypoints[1], 5.);
+        assert_close(waypoints[2], 20.);
+        assert_close(path.cost, 15. + 10. * FERRY_COST + 15.);
+    }
+
+    #[test]
+    fn adds_up_costs_of_several_waters() {
+        let mut graph = SurfaceGraph::default();
+        graph.add_feature(degrees(90.), 5., FERRY_COST);
+        graph.add_feature(degrees(180.), 5., FERRY_COST);
+        graph.add_feature(degrees(300.), 10., SWAMP_COST);
+        let path = graph.find_path(degrees(60.), degrees(210.)).unwrap();
+        assert_eq!(path.direction, 1);
+        assert_close(path.cost, 130. + 20. * FERRY_COST);
+        // The long way round only crosses the swamp, but is still further.
+        let around = graph.route(degrees(60.), degrees(210.), -1).unwrap();
+        assert_close(around.cost, 190. + 20. * SWAMP_COST);
+    }
+
+    #[test]
+    fn no_path_when_walled_in_on_both_sides() {
+        let mut graph = SurfaceGraph::default();
+        graph.add_feature(degrees(90.), 5., f32::INFINITY);
+        graph.add_feature(degrees(355.), 10., f32::INFINITY);
+        assert!(graph.find_path(degrees(30.), degrees(180.)).is_none());
+        assert!(graph.find_path(degrees(180.), degrees(30.)).is_none());
+        assert!(graph.find_path(degrees(30.), degrees(60.)).is_some());
+    }
+
+    #[test]
+    fn can_step_off_water_it_stands_in() {
+        let mut graph = SurfaceGraph::default();
+        graph.add_feature(degrees(0.), 10., f32::INFINITY);
+        let path = graph.find_path(degrees(355.), degrees(30.)).unwrap();
+        assert_eq!(path.direction, 1);
+        assert_close(path.cost, 35.);
+    }
+}
@@ -1,18 +1,58 @@
 
 use bevy::{
     prelude::*, 
-    render::render_resource::{AsBindGroup, ShaderRef, ShaderType}, sprite::{Material2d, Material2dPlugin},
+    render::{render_resource::{AsBindGroup, ShaderRef, ShaderType}, storage::ShaderStorageBuffer}, sprite::{Material2d, Material2dPlugin}, utils::HashMap,
 };
 
-use crate::planet_sticker::PlanetSticker;
+use crate::{bridge::{is_bridged, Bridge}, elevation::{PlanetElevation, ELEVATION_SAMPLES}, looping_float::LoopingFloat, natural_resource::Biome, planet_sticker::PlanetSticker};
 
 #[derive(Component, PartialEq)]
 pub struct PlanetWater {
 }
 
-#[derive(Component, Default)]
+// A bowl dug into the ground, spanning the sticker's size.
+#[derive(Component)]
+pub struct PlanetCrater {
+    // World units below the surface at its deepest point.
+    pub depth: f32,
+}
+
+// Forces the biome of the sticker's span, regardless of how far the water is.
+#[derive(Component)]
+pub struct PlanetBiome {
+    pub biome: Biome,
+}
+
+// Stickers standing on the surface, leaving out the areas that shape it.
+pub type OnSurface = (Without<PlanetWater>, Without<PlanetBiome>, Without<PlanetCrater>);
+
+#[derive(Component)]
 pub struct Planet {
     pub radius: f32,
+    // Relative to the main planet's. Heavier gravity slows villagers down.
+    pub gravity: f32,
+    // Density of the air relative to the main planet's. Thinner air scatters less light into the night.
+    pub atmosphere: f32,
+}
+
+// Lights the planets, see the day cycle.
+#[derive(Component)]
+pub struct Star;
+
+pub struct Orbit {
+    pub center: Entity,
+    pub distance: f32,
+    pub period: f32,
+    pub degrees: LoopingFloat<360>,
+}
+
+// Planets and stars are top level entities, so their Transform is also where they are in the world.
+#[derive(Component, Default)]
+pub struct PlanetMotion {
+    // Seconds per turn, counter-clockwise. Zero keeps the planet still.
+    pub spin_period: f32,
+    pub spin_degrees: LoopingFloat<360>,
+    pub orbit: Option<Orbit>,
 }
 
 #[derive(Resource, Default)]
@@ -27,7 +67,8 @@ impl Plugin for PlanetsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Planets::default())
         .add_plugins(Material2dPlugin::<PlanetMaterial>::default())
-        .add_systems(Update, update_water);
+        .add_systems(PreUpdate, move_planets)
+        .add_systems(Update, update_planet_features);
     }
 }
 
@@ -35,6 +76,12 @@ impl Plugin for PlanetsPlugin {
 pub struct PlanetMaterial {
     #[uniform(0)]
     pub settings: PlanetSettings,
+    // Holds a PlanetFeature array, see update_planet_features.
+    #[storage(1, read_only)]
+    pub features: Handle<ShaderStorageBuffer>,
+    // Holds the PlanetElevation samples.
+    #[storage(2, read_only)]
+    pub elevation: Handle<ShaderStorageBuffer>,
 }
 
 impl Material2d for PlanetMaterial {
@@ -45,28 +92,139 @@ impl Material2d for PlanetMaterial {
 
 #[derive(ShaderType, Debug, Clone)]
 pub struct PlanetSettings {
-    pub hole_array: [Vec4; 8],
+    pub radius: f32,
+    // Half the size of the planet's quad, which reaches past the radius to fit the hills.
+    pub extent: f32,
+    pub feature_count: u32,
+    pub sun_degrees: f32,
+    pub night_brightness: f32,
+}
+
+const FEATURE_WATER: u32 = 0;
+const FEATURE_BIOME: u32 = 1;
+const FEATURE_CRATER: u32 = 2;
+
+#[derive(ShaderType, Debug, Clone, Default)]
+pub struct PlanetFeature {
+    pub start_degrees: f32,
+    pub end_degrees: f32,
+    pub kind: u32,
+    // Whether water is bridged, the biome index or the crater depth as a share of the radius.
+    pub data: f32,
+}
+
+impl PlanetFeature {
+    fn new(sticker: &PlanetSticker, kind: u32, data: f32) -> Option<PlanetFeature> {
+        let size = sticker.size_degrees?;
+        let position = sticker.position_degrees.to_f32();
+        return Some(PlanetFeature {
+            start_degrees: position - size / 2.,
+            end_degrees: position + size / 2.,
+            kind,
+            data,
+        });
+    }
 }
 
+// Storage buffers can't be empty, so a planet without features still gets one that is never read.
+pub fn new_features_buffer() -> ShaderStorageBuffer {
+    return ShaderStorageBuffer::from(vec![PlanetFeature::default()]);
+}
+
+// Flat ground until the planet's elevation is baked.
+pub fn new_elevation_buffer() -> ShaderStorageBuffer {
+    return ShaderStorageBuffer::from(vec![0f32; ELEVATION_SAMPLES]);
+}
 
-fn update_water(
-    mut planets: Query<(Entity, &MeshMaterial2d<PlanetMaterial>), With<Planet>>,
+
+fn move_planets(
+    mut motions_query: Query<(Entity, &mut PlanetMotion, &mut Transform)>,
+    centers_query: Query<&Transform, Without<PlanetMotion>>,
+    time: Res<Time>,
+) {
+    let mut placed: HashMap<Entity, Vec3> = HashMap::new();
+    for (entity, mut motion, mut transform) in motions_query.iter_mut() {
+        if motion.spin_period > 0. {
+            let step = 360. / motion.spin_period * time.delta_secs();
+            motion.spin_degrees += step;
+        }
+        transform.rotation = Quat::from_rotation_z(motion.spin_degrees.to_f32().to_radians());
+        match &mut motion.orbit {
+            Some(orbit) => {
+                if orbit.period > 0. {
+                    orbit.degrees += 360. / orbit.period * time.delta_secs();
+                }
+            }
+            None => {
+                placed.insert(entity, transform.translation);
+            }
+        }
+    }
+    // Each pass places the bodies whose center is already where it is this frame, so moons follow their planet.
+    loop {
+        let mut progressed = false;
+        for (entity, motion, mut transform) in motions_query.iter_mut() {
+            let Some(orbit) = &motion.orbit else { continue; };
+            if placed.contains_key(&entity) { continue; }
+            let center = placed.get(&orbit.center).copied()
+                .or_else(|| centers_query.get(orbit.center).ok().map(|center| center.translation));
+            let Some(center) = center else { continue; };
+            let angle = orbit.degrees.to_f32().to_radians();
+            let position = center.xy() + Vec2::new(angle.sin(), angle.cos()) * orbit.distance;
+            transform.translation = position.extend(transform.translation.z);
+            placed.insert(entity, transform.translation);
+            progressed = true;
+        }
+        if !progressed { break; }
+    }
+}
+
+fn update_planet_features(
+    planets: Query<(Entity, &Planet, Ref<MeshMaterial2d<PlanetMaterial>>, Option<Ref<PlanetElevation>>)>,
     waters_query: Query<&PlanetSticker, With<PlanetWater>>,
+    bridges_query: Query<&PlanetSticker, With<Bridge>>,
+    biomes_query: Query<(&PlanetSticker, &PlanetBiome)>,
+    craters_query: Query<(&PlanetSticker, &PlanetCrater)>,
+    changed_query: Query<(), (Or<(With<PlanetWater>, With<Bridge>, With<PlanetBiome>, With<PlanetCrater>)>, Changed<PlanetSticker>)>,
+    mut removed_waters: RemovedComponents<PlanetWater>,
+    mut removed_bridges: RemovedComponents<Bridge>,
+    mut removed_biomes: RemovedComponents<PlanetBiome>,
+    mut removed_craters: RemovedComponents<PlanetCrater>,
     mut materials: ResMut<Assets<PlanetMaterial>>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
 ) {
-    for (planet, handle) in planets.iter_mut() {
-        if let Some(material) = materials.get_mut(handle.id()) {
-            let mut waters: [Vec4; 8] = [Vec4::splat(0.); 8];
-            let mut index = 0;
-            for water_sticker in waters_query.iter() {
-                let Some(water_planet) = water_sticker.planet else { continue; };
-                let Some(size) = water_sticker.size_degrees else { continue; };
-                if water_planet != planet {continue;};
-                waters[index] = Vec4::new(water_sticker.position_degrees.to_f32() - size / 2., water_sticker.position_degrees.to_f32() + size / 2., 0., 0.);
-                index += 1;
-                if index == 8 {break;};
+    // Touching the material or its buffers uploads them to the GPU again, so only do it when something moved.
+    let removed = removed_waters.read().count() + removed_bridges.read().count() + removed_biomes.read().count() + removed_craters.read().count() > 0;
+    let features_changed = removed || !changed_query.is_empty();
+    for (planet, planet_info, handle, elevation) in planets.iter() {
+        let elevation_changed = elevation.as_ref().is_some_and(|elevation| elevation.is_changed());
+        if !features_changed && !elevation_changed && !handle.is_added() { continue; }
+        let Some(material) = materials.get_mut(handle.id()) else { continue; };
+        if let Some(elevation) = elevation.filter(|elevation| elevation.is_changed()) {
+            if let Some(buffer) = buffers.get_mut(&material.elevation) {
+                buffer.set_data(elevation.samples.clone());
             }
-            material.settings.hole_array = waters;
         }
+        let on_planet = |sticker: &PlanetSticker| sticker.planet == Some(planet);
+        let mut features: Vec<PlanetFeature> = vec![];
+        // Biomes go first so that water and craters are drawn over them.
+        for (sticker, biome) in biomes_query.iter().filter(|(sticker, _)| on_planet(sticker)) {
+            features.extend(PlanetFeature::new(sticker, FEATURE_BIOME, biome.biome as u32 as f32));
+        }
+        for sticker in waters_query.iter().filter(|sticker| on_planet(sticker)) {
+            // Tells the shader to draw a deck across the hole.
+            let bridged = if is_bridged(sticker, bridges_query.iter()) { 1. } else { 0. };
+            features.extend(PlanetFeature::new(sticker, FEATURE_WATER, bridged));
+        }
+        // The bowl itself is part of the elevation, the shader only scorches the ground in it.
+        for (sticker, crater) in craters_query.iter().filter(|(sticker, _)| on_planet(sticker)) {
+            features.extend(PlanetFeature::new(sticker, FEATURE_CRATER, crater.depth / planet_info.radius.max(1.)));
+        }
+        material.settings.feature_count = features.len() as u32;
+        if features.is_empty() {
+            features.push(PlanetFeature::default());
+        }
+        let Some(buffer) = buffers.get_mut(&material.features) else { continue; };
+        buffer.set_data(features);
     }
-}
\ No newline at end of file
+}
@@ -2,30 +2,248 @@ use approx::AbsDiffEq;
 use bevy::{
     prelude::*, render::render_resource::{AsBindGroup, ShaderRef, ShaderType}, sprite::{AlphaMode2d, Anchor, Material2d}
 };
-use crate::{blinking_sprite::BlinkingSprite, looping_float::LoopingFloat, mouse_position::MousePosition, planet::{Planet, Planets}, planet_sticker::{IsCollidingWith, PlanetSticker}, spawn_building, natural_resource::NaturalResource, ResourceType};
+use crate::{blinking_sprite::BlinkingSprite, bridge::MAX_BRIDGE_LENGTH, processing::{Recipe, MEALS_RECIPE, PLANKS_RECIPE}, looping_float::LoopingFloat, mouse_position::MousePosition, planet::{OnSurface, Planet, PlanetBiome, PlanetWater, Planets}, planet_sticker::{Contains, EdgeDistanceTo, IsCollidingWith, PlanetSticker}, construction::spawn_construction_site, natural_resource::{determine_biome, Biome, NaturalResource}, occupable::{Occupable, OccupableType, SelectedOccupable}, planet_villager::VillagerWorking, resources::Resources, ResourceType};
 
 #[derive(Component)]
 pub struct PlanetPlacingGhost;
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum BuildingType {
-    Sawmill = 32
+    Sawmill = 32,
+    House = 33,
+    Storage = 34,
+    Carpenter = 35,
+    Kitchen = 36,
+    Quarry = 37,
+    Port = 38,
+    Bridge = 39,
+}
+
+const BUILDING_HOTKEYS: [(KeyCode, BuildingType); 8] = [
+    (KeyCode::Space, BuildingType::Sawmill),
+    (KeyCode::KeyH, BuildingType::House),
+    (KeyCode::KeyJ, BuildingType::Storage),
+    (KeyCode::KeyK, BuildingType::Carpenter),
+    (KeyCode::KeyL, BuildingType::Kitchen),
+    (KeyCode::KeyM, BuildingType::Quarry),
+    (KeyCode::KeyN, BuildingType::Port),
+    (KeyCode::KeyB, BuildingType::Bridge),
+];
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PlacementError {
+    Colliding,
+    OnWater,
+    CannotAfford,
+    WrongBiome,
+    NeedsWater,
+    NotOverWater,
+    TooWide,
+}
+
+impl PlacementError {
+    pub fn description(&self) -> &'static str {
+        match self {
+            PlacementError::Colliding => "Too close to another building",
+            PlacementError::OnWater => "Cannot build on water",
+            PlacementError::CannotAfford => "Not enough resources",
+            PlacementError::WrongBiome => "Wrong terrain for this building",
+            PlacementError::NeedsWater => "Must be built next to water",
+            PlacementError::NotOverWater => "Must be built across water",
+            PlacementError::TooWide => "Too wide to bridge",
+        }
+    }
 }
 
 pub struct BuildingInfo {
-    pub exploited_resource: ResourceType,
+    pub name: &'static str,
+    pub exploited_resources: &'static [ResourceType],
+    // Kinds of nodes the workers go out to, so a port only fishes even though berries are food too.
+    pub worked_occupables: &'static [OccupableType],
+    pub range: f32,
+    pub max_workers: u32,
+    pub beds: u32,
+    pub recipe: Option<Recipe>,
+    pub cost: &'static [(ResourceType, i32)],
+    pub build_time: f32,
+    pub biomes: &'static [Biome],
+    pub needs_water: bool,
+    pub spans_water: bool,
+    pub texture_path: &'static str,
+}
+
+pub struct UpgradeInfo {
+    pub cost: &'static [(ResourceType, i32)],
     pub range: f32,
+    pub max_workers: u32,
+    pub texture_path: &'static str,
+}
+
+#[derive(Component)]
+pub struct Building {
+    pub building_type: BuildingType,
+    pub level: u32,
 }
 
 pub trait GetBuildingInfo {
     fn get_building_info(&self) -> BuildingInfo;
+    fn get_upgrade_info(&self, level: u32) -> Option<UpgradeInfo>;
 }
 
 impl GetBuildingInfo for BuildingType {
     fn get_building_info(&self) -> BuildingInfo
     {
         match self {
-            BuildingType::Sawmill => BuildingInfo { exploited_resource: ResourceType::Wood, range: 64. },
+            BuildingType::Sawmill => BuildingInfo {
+                name: "Sawmill",
+                exploited_resources: &[ResourceType::Wood],
+                worked_occupables: &[OccupableType::Cutting],
+                range: 64.,
+                max_workers: 3,
+                beds: 0,
+                recipe: None,
+                cost: &[(ResourceType::Wood, 4)],
+                build_time: 8.,
+                biomes: &[],
+                needs_water: false,
+                spans_water: false,
+                texture_path: "buildings/sawmill.png",
+            },
+            BuildingType::House => BuildingInfo {
+                name: "House",
+                exploited_resources: &[],
+                worked_occupables: &[],
+                range: 0.,
+                max_workers: 0,
+                beds: 3,
+                recipe: None,
+                cost: &[(ResourceType::Wood, 6)],
+                build_time: 10.,
+                biomes: &[],
+                needs_water: false,
+                spans_water: false,
+                texture_path: "buildings/house.png",
+            },
+            BuildingType::Storage => BuildingInfo {
+                name: "Storage",
+                exploited_resources: &[],
+                worked_occupables: &[],
+                range: 0.,
+                max_workers: 0,
+                beds: 0,
+                recipe: None,
+                cost: &[(ResourceType::Wood, 4)],
+                build_time: 6.,
+                biomes: &[],
+                needs_water: false,
+                spans_water: false,
+                texture_path: "buildings/storage.png",
+            },
+            BuildingType::Carpenter => BuildingInfo {
+                name: "Carpenter",
+                exploited_resources: &[],
+                worked_occupables: &[],
+                range: 0.,
+                max_workers: 2,
+                beds: 0,
+                recipe: Some(PLANKS_RECIPE),
+                cost: &[(ResourceType::Wood, 8), (ResourceType::Stone, 4)],
+                build_time: 12.,
+                biomes: &[],
+                needs_water: false,
+                spans_water: false,
+                texture_path: "buildings/carpenter.png",
+            },
+            BuildingType::Kitchen => BuildingInfo {
+                name: "Kitchen",
+                exploited_resources: &[],
+                worked_occupables: &[],
+                range: 0.,
+                max_workers: 2,
+                beds: 0,
+                recipe: Some(MEALS_RECIPE),
+                cost: &[(ResourceType::Wood, 6), (ResourceType::Stone, 6)],
+                build_time: 12.,
+                biomes: &[],
+                needs_water: false,
+                spans_water: false,
+                texture_path: "buildings/kitchen.png",
+            },
+            BuildingType::Quarry => BuildingInfo {
+                name: "Quarry",
+                exploited_resources: &[ResourceType::Stone, ResourceType::IronOre],
+                worked_occupables: &[OccupableType::Mining],
+                range: 48.,
+                max_workers: 3,
+                beds: 0,
+                recipe: None,
+                cost: &[(ResourceType::Wood, 8)],
+                build_time: 10.,
+                biomes: &[Biome::Highlands],
+                needs_water: false,
+                spans_water: false,
+                texture_path: "buildings/quarry.png",
+            },
+            BuildingType::Port => BuildingInfo {
+                name: "Port",
+                exploited_resources: &[ResourceType::Food],
+                worked_occupables: &[OccupableType::Fishing],
+                range: 64.,
+                max_workers: 2,
+                beds: 0,
+                recipe: None,
+                cost: &[(ResourceType::Wood, 6)],
+                build_time: 8.,
+                biomes: &[],
+                needs_water: true,
+                spans_water: false,
+                texture_path: "buildings/port.png",
+            },
+            BuildingType::Bridge => BuildingInfo {
+                name: "Bridge",
+                exploited_resources: &[],
+                worked_occupables: &[],
+                range: 0.,
+                max_workers: 0,
+                beds: 0,
+                recipe: None,
+                cost: &[(ResourceType::Wood, 10), (ResourceType::Stone, 4)],
+                build_time: 14.,
+                biomes: &[],
+                needs_water: false,
+                spans_water: true,
+                texture_path: "buildings/bridge.png",
+            },
+        }
+    }
+
+    fn get_upgrade_info(&self, level: u32) -> Option<UpgradeInfo> {
+        match (self, level) {
+            (BuildingType::Sawmill, 2) => Some(UpgradeInfo {
+                cost: &[(ResourceType::Wood, 10), (ResourceType::Planks, 4)],
+                range: 96.,
+                max_workers: 4,
+                texture_path: "buildings/sawmill_2.png",
+            }),
+            (BuildingType::Sawmill, 3) => Some(UpgradeInfo {
+                cost: &[(ResourceType::Planks, 10), (ResourceType::Stone, 10)],
+                range: 128.,
+                max_workers: 5,
+                texture_path: "buildings/sawmill_3.png",
+            }),
+            (BuildingType::Quarry, 2) => Some(UpgradeInfo {
+                cost: &[(ResourceType::Wood, 10), (ResourceType::Stone, 10)],
+                range: 64.,
+                max_workers: 4,
+                texture_path: "buildings/quarry_2.png",
+            }),
+            (BuildingType::Quarry, 3) => Some(UpgradeInfo {
+                cost: &[(ResourceType::Planks, 10), (ResourceType::IronOre, 6)],
+                range: 80.,
+                max_workers: 5,
+                texture_path: "buildings/quarry_3.png",
+            }),
+            _ => None,
         }
     }
 }
@@ -33,22 +251,33 @@ impl GetBuildingInfo for BuildingType {
 #[derive(Resource, Default)]
 pub struct PlanetPlacing {
     building_type: Option<BuildingType>,
+    error: Option<PlacementError>,
 }
 
+#[derive(Component)]
+struct PlacementTooltip;
+
+#[derive(Component)]
+struct PlacingRangeArc;
+
+// Drawn from the planet's center so the band can follow the surface around the building.
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
-pub struct CircleMaterial {
+pub struct RangeArcMaterial {
     #[uniform(0)]
-    settings: CircleSettings,
+    settings: RangeArcSettings,
 }
 
-#[derive(ShaderType, Debug, Clone)]
-struct CircleSettings {
+#[derive(ShaderType, Debug, Clone, Default)]
+struct RangeArcSettings {
     radius: f32,
+    extent: f32,
+    center_degrees: f32,
+    half_span_degrees: f32,
 }
 
-impl Material2d for CircleMaterial {
+impl Material2d for RangeArcMaterial {
     fn fragment_shader() -> ShaderRef {
-        "shaders/circle.wgsl".into()
+        "shaders/range_arc.wgsl".into()
     }
 
     fn alpha_mode(&self) -> AlphaMode2d {
@@ -62,14 +291,16 @@ impl Plugin for PlanetPlacingPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(PlanetPlacing::default())
         .add_systems(Startup, spawn_ghost)
-        .add_systems(Update, (handle_ghost, handle_circle, blink_resource_in_range));
+        .add_systems(Update, (handle_ghost, validate_placement, place_building, update_placement_tooltip).chain())
+        .add_systems(Update, (handle_range_arc, blink_resource_in_range));
     }
 }
 
 fn spawn_ghost(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut circle_materials: ResMut<Assets<CircleMaterial>>,
+    mut range_arc_materials: ResMut<Assets<RangeArcMaterial>>,
+    asset_server: Res<AssetServer>,
 ) {
     commands.spawn((
         Sprite::default(),
@@ -79,38 +310,103 @@ fn spawn_ghost(
             ..default()
         },
         Name::new("PlacingGhost")
-    )).with_children(|parent| {
-        parent.spawn((
-            Mesh2d(meshes.add(Rectangle { half_size: Vec2::new(100., 100.)})),
-            MeshMaterial2d(circle_materials.add(CircleMaterial { settings: CircleSettings { radius: 50. }})),
-            Name::new("PlacingGhostCircle")
-        ));
-    });
-    commands.insert_resource(PlanetPlacing { building_type: None })
-}
-
-fn handle_circle(
-    circle_query: Query<&MeshMaterial2d<CircleMaterial>>,
+    ));
+    let range_arc = spawn_range_arc(&mut commands, &mut meshes, &mut range_arc_materials);
+    commands.entity(range_arc).insert((PlacingRangeArc, Name::new("PlacingRangeArc")));
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/pixel.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        BackgroundColor(Color::BLACK),
+        Visibility::Hidden,
+        PlacementTooltip,
+        Name::new("PlacementTooltip")
+    ));
+    commands.insert_resource(PlanetPlacing { building_type: None, error: None })
+}
+
+fn handle_range_arc(
+    mut arc_query: Query<(&mut Transform, &mut Visibility, &MeshMaterial2d<RangeArcMaterial>), With<PlacingRangeArc>>,
+    ghost_query: Query<&PlanetSticker, With<PlanetPlacingGhost>>,
+    planets_query: Query<(&Planet, &GlobalTransform)>,
     planet_placing: Res<PlanetPlacing>,
-    mut circle_materials: ResMut<Assets<CircleMaterial>>,
+    mut range_arc_materials: ResMut<Assets<RangeArcMaterial>>,
 ) {
-    let Some(building_type) = &planet_placing.building_type else { return; };
-    for handle in circle_query.iter() {
-        if let Some(material) = circle_materials.get_mut(handle.id()) {
-            material.settings.radius = building_type.get_building_info().range;
-        }
+    let ghost = ghost_query.single();
+    for (mut transform, mut visibility, handle) in arc_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+        let Some(building_type) = &planet_placing.building_type else { continue; };
+        let Some(planet_entity) = ghost.planet else { continue; };
+        let Ok((planet, planet_transform)) = planets_query.get(planet_entity) else { continue; };
+        let Some(material) = range_arc_materials.get_mut(handle.id()) else { continue; };
+        let range = building_type.get_building_info().range;
+        update_range_arc(&mut transform, &mut visibility, material, planet, planet_transform, ghost.position_degrees.to_f32(), range);
+    }
+}
+
+pub fn update_range_arc(
+    transform: &mut Transform,
+    visibility: &mut Visibility,
+    material: &mut RangeArcMaterial,
+    planet: &Planet,
+    planet_transform: &GlobalTransform,
+    center_degrees: f32,
+    range: f32,
+) {
+    if range <= 0. {
+        *visibility = Visibility::Hidden;
+        return;
     }
+    *visibility = Visibility::Visible;
+    let extent = planet.radius + 8.;
+    transform.translation = planet_transform.translation().xy().extend(1.);
+    transform.rotation = planet_transform.rotation();
+    transform.scale = Vec3::new(extent, extent, 1.);
+    material.settings = RangeArcSettings {
+        radius: planet.radius,
+        extent,
+        center_degrees,
+        half_span_degrees: arc_half_span(range, planet.radius),
+    };
+}
+
+pub fn spawn_range_arc(commands: &mut Commands, meshes: &mut ResMut<Assets<Mesh>>, range_arc_materials: &mut ResMut<Assets<RangeArcMaterial>>) -> Entity {
+    return commands.spawn((
+        Mesh2d(meshes.add(Rectangle { half_size: Vec2::ONE })),
+        MeshMaterial2d(range_arc_materials.add(RangeArcMaterial { settings: RangeArcSettings::default() })),
+        Visibility::Hidden,
+        Name::new("RangeArc")
+    )).id();
+}
+
+// The angle covered on each side by everything within `range` of arc length, matching `arc_distance`.
+pub fn arc_half_span(range: f32, radius: f32) -> f32 {
+    return (range / radius).to_degrees().min(180.);
 }
 
 fn blink_resource_in_range(
     planets_query: Query<&Planet>,
     ghost_query: Query<&PlanetSticker, With<PlanetPlacingGhost>>,
-    mut natural_resource_query: Query<(&NaturalResource, &PlanetSticker, &mut BlinkingSprite), Without<PlanetPlacingGhost>>,
+    mut natural_resource_query: Query<(Entity, &NaturalResource, &Occupable, &PlanetSticker, &mut BlinkingSprite), Without<PlanetPlacingGhost>>,
+    workers_query: Query<&VillagerWorking>,
     planet_placing: Res<PlanetPlacing>,
+    selected_occupable: Res<SelectedOccupable>,
 ) {
     let ghost = ghost_query.single();
-    for (natural_resource, resource_sticker, mut blinking) in natural_resource_query.iter_mut() {
-        blinking.enabled = false;
+    // Resources the selected building's workers are currently harvesting.
+    let targeted: Vec<Entity> = workers_query.iter()
+        .filter(|worker| Some(worker.current_occupable) == selected_occupable.occupable)
+        .map(|worker| worker.current_work)
+        .collect();
+    for (natural_resource_entity, natural_resource, occupable, resource_sticker, mut blinking) in natural_resource_query.iter_mut() {
+        blinking.enabled = targeted.contains(&natural_resource_entity);
         let Some(building_type) = &planet_placing.building_type else { 
             continue;
         };
@@ -118,49 +414,43 @@ fn blink_resource_in_range(
         let Ok(planet) = planets_query.get(planet_entity) else { continue; };
         let info = building_type.get_building_info();
         let arc_distance = resource_sticker.position_degrees.arc_distance(ghost.position_degrees.to_f32(), planet.radius);
-        if arc_distance <= info.range && natural_resource.produced_resource == info.exploited_resource {
+        let exploited = info.exploited_resources.contains(&natural_resource.produced_resource)
+            && info.worked_occupables.contains(&occupable.occupable_type);
+        if arc_distance <= info.range && exploited {
             blinking.enabled = true;
         }
     }
 }
 
 fn handle_ghost(
-    mut commands: Commands,
     keys: Res<ButtonInput<KeyCode>>,
-    mouse_buttons: Res<ButtonInput<MouseButton>>,
     mouse_position: Res<MousePosition>,
     asset_server: Res<AssetServer>,
     planets: Res<Planets>,
     mut planet_placing: ResMut<PlanetPlacing>,
     mut ghost_query: Query<(&mut Transform, &mut Visibility, &mut PlanetSticker, &mut Sprite), With<PlanetPlacingGhost>>,
     planets_query: Query<(Entity, &Planet, &GlobalTransform)>,
-    stickers_query: Query<&PlanetSticker, Without<PlanetPlacingGhost>>,
+    waters_query: Query<&PlanetSticker, (With<PlanetWater>, Without<PlanetPlacingGhost>)>,
 ) {
     let (mut ghost_transform, mut ghost_visibility, mut ghost_sticker, mut ghost_sprite) = ghost_query.single_mut();
 
-    if keys.just_pressed(KeyCode::Space) {
-        planet_placing.building_type = Some(BuildingType::Sawmill);
+    for (key, building_type) in BUILDING_HOTKEYS {
+        if keys.just_pressed(key) {
+            planet_placing.building_type = Some(building_type);
+        }
     }
-    ghost_sprite.color.set_alpha(1.);
-    if let Some(building_type) = &planet_placing.building_type {
+    if let Some(building_type) = planet_placing.building_type {
         *ghost_visibility = Visibility::Visible;
-        ghost_sprite.color.set_alpha(0.5);
-        let image: Handle<Image>;
-        match building_type {
-            BuildingType::Sawmill => image = asset_server.load("buildings/sawmill.png"),
-        }
-        ghost_sprite.image = image;
+        ghost_sprite.image = asset_server.load(building_type.get_building_info().texture_path);
         if let Some((planet_entity, angle)) = find_closest_surface(mouse_position.world_position, &planets.all, &planets_query, 20.) {
             ghost_sticker.planet = Some(planet_entity);
             ghost_sticker.position_degrees = LoopingFloat::new(angle);
             ghost_sprite.anchor = Anchor::BottomCenter;
-            let colliding = check_planet_collisions(ghost_sticker.as_ref(), &stickers_query);
-            if colliding {
-                ghost_sprite.color.set_alpha(0.1);
-            } else {
-                if mouse_buttons.just_pressed(MouseButton::Left) {
-                    spawn_building(&mut commands, &asset_server, planet_entity, angle, BuildingType::Sawmill);
-                    planet_placing.building_type = None;
+            // Bridges snap to the middle of the water they span.
+            if building_type.get_building_info().spans_water {
+                let spanned = waters_query.iter().find(|water| water.planet == Some(planet_entity) && water.contains(angle));
+                if let Some(water) = spanned {
+                    ghost_sticker.position_degrees = water.position_degrees;
                 }
             }
         } else {
@@ -174,6 +464,120 @@ fn handle_ghost(
     }
 }
 
+fn validate_placement(
+    mut planet_placing: ResMut<PlanetPlacing>,
+    resources: Res<Resources>,
+    ghost_query: Query<&PlanetSticker, With<PlanetPlacingGhost>>,
+    waters_query: Query<&PlanetSticker, With<PlanetWater>>,
+    biomes_query: Query<(&PlanetSticker, &PlanetBiome)>,
+    stickers_query: Query<&PlanetSticker, (Without<PlanetPlacingGhost>, OnSurface)>,
+    planets_query: Query<&Planet>,
+) {
+    let ghost = ghost_query.single();
+    planet_placing.error = None;
+    let Some(building_type) = planet_placing.building_type else { return; };
+    let Some(planet) = ghost.planet else { return; };
+    let Ok(planet_info) = planets_query.get(planet) else { return; };
+    let info = building_type.get_building_info();
+    planet_placing.error = check_placement(&info, ghost, planet, planet_info.radius, &resources, &waters_query, &biomes_query, &stickers_query).err();
+}
+
+fn check_placement(
+    info: &BuildingInfo,
+    ghost: &PlanetSticker,
+    planet: Entity,
+    planet_radius: f32,
+    resources: &Resources,
+    waters_query: &Query<&PlanetSticker, With<PlanetWater>>,
+    biomes_query: &Query<(&PlanetSticker, &PlanetBiome)>,
+    stickers_query: &Query<&PlanetSticker, (Without<PlanetPlacingGhost>, OnSurface)>,
+) -> Result<(), PlacementError> {
+    let half_size = ghost.size_degrees.unwrap_or(0.) / 2.;
+    let mut touches_water = false;
+    let mut spanned_water = None;
+    for water in waters_query.iter() {
+        if water.planet != Some(planet) { continue; }
+        if info.spans_water {
+            if water.contains(ghost.position_degrees.to_f32()) {
+                spanned_water = Some(water);
+            }
+            continue;
+        }
+        // Ports may hang over the shore, everything else has to stay on dry land.
+        if water.contains(ghost.position_degrees.to_f32()) || (!info.needs_water && ghost.is_colliding_with(water)) {
+            return Err(PlacementError::OnWater);
+        }
+        if water.edge_distance_to(ghost.position_degrees.to_f32()) <= half_size + 2. {
+            touches_water = true;
+        }
+    }
+    if info.spans_water {
+        let Some(water) = spanned_water else { return Err(PlacementError::NotOverWater); };
+        let width = water.size_degrees.unwrap_or(0.).to_radians() * planet_radius;
+        if width > MAX_BRIDGE_LENGTH {
+            return Err(PlacementError::TooWide);
+        }
+    }
+    if check_planet_collisions(ghost, stickers_query) {
+        return Err(PlacementError::Colliding);
+    }
+    if info.needs_water && !touches_water {
+        return Err(PlacementError::NeedsWater);
+    }
+    if !info.biomes.is_empty() {
+        let biome = determine_biome(planet, ghost.position_degrees.to_f32(), waters_query, biomes_query);
+        if !biome.is_some_and(|biome| info.biomes.contains(&biome)) {
+            return Err(PlacementError::WrongBiome);
+        }
+    }
+    if !resources.can_afford(info.cost) {
+        return Err(PlacementError::CannotAfford);
+    }
+    return Ok(());
+}
+
+fn place_building(
+    mut commands: Commands,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    asset_server: Res<AssetServer>,
+    mut planet_placing: ResMut<PlanetPlacing>,
+    mut ghost_query: Query<(&PlanetSticker, &mut Sprite), With<PlanetPlacingGhost>>,
+    mut resources: ResMut<Resources>,
+) {
+    let (ghost_sticker, mut ghost_sprite) = ghost_query.single_mut();
+    let Some(building_type) = planet_placing.building_type else { return; };
+    let Some(planet_entity) = ghost_sticker.planet else {
+        ghost_sprite.color = Color::srgba(1., 1., 1., 0.5);
+        return;
+    };
+    if planet_placing.error.is_some() {
+        ghost_sprite.color = Color::srgba(1., 0.3, 0.3, 0.25);
+        return;
+    }
+    ghost_sprite.color = Color::srgba(0.5, 1., 0.5, 0.6);
+    if mouse_buttons.just_pressed(MouseButton::Left) {
+        spawn_construction_site(&mut commands, &asset_server, planet_entity, ghost_sticker.position_degrees.to_f32(), building_type, &mut resources);
+        planet_placing.building_type = None;
+    }
+}
+
+fn update_placement_tooltip(
+    planet_placing: Res<PlanetPlacing>,
+    window_query: Query<&Window>,
+    mut tooltip_query: Query<(&mut Text, &mut Node, &mut Visibility), With<PlacementTooltip>>,
+) {
+    let Ok((mut text, mut node, mut visibility)) = tooltip_query.get_single_mut() else { return; };
+    let cursor = window_query.get_single().ok().and_then(|window| window.cursor_position());
+    let (Some(error), Some(cursor)) = (planet_placing.error, cursor) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Visible;
+    text.0 = error.description().to_string();
+    node.left = Val::Px(cursor.x + 16.);
+    node.top = Val::Px(cursor.y + 16.);
+}
+
 fn find_closest_surface(pos: Vec2, planets: &Vec<Entity>, planets_query: &Query<(Entity, &Planet, &GlobalTransform)>, threshold: f32) -> Option<(Entity, f32)> {
     let mut best: Option<(Entity, f32)> = None;
     for planet_id in planets {
@@ -191,11 +595,11 @@ fn find_closest_surface(pos: Vec2, planets: &Vec<Entity>, planets_query: &Query<
     return best;
 }
 
-fn check_planet_collisions(sticker: &PlanetSticker, stickers_query: &Query<&PlanetSticker, Without<PlanetPlacingGhost>>) -> bool{
+fn check_planet_collisions(sticker: &PlanetSticker, stickers_query: &Query<&PlanetSticker, (Without<PlanetPlacingGhost>, OnSurface)>) -> bool{
     for other_sticker in stickers_query.iter() {
         if sticker.is_colliding_with(other_sticker) {
             return true
         }
     }
     return false;
-}
\ No newline at end of file
+}
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::elevation::PlanetElevation;
 use crate::looping_float::LoopingFloat;
 use crate::planet::Planet;
 
@@ -89,24 +90,25 @@ impl Plugin for PlanetStickerPlugin {
     }
 }
 
+// Where a spot on a planet's surface is in the world, and which way is up there, turning along with the planet.
+pub fn surface_point(planet_transform: &Transform, radius: f32, position_degrees: f32) -> (Vec3, Quat) {
+    let rotation = planet_transform.rotation * Quat::from_rotation_z(-position_degrees.to_radians());
+    return (planet_transform.translation + rotation * Vec3::new(0., radius, 0.), rotation);
+}
+
 fn stick_to_planet(
     mut sticker_query: Query<(&mut Transform, &PlanetSticker)>,
-    targets: Query<(&GlobalTransform, &Planet)>,
+    targets: Query<(&Transform, &Planet, Option<&PlanetElevation>), Without<PlanetSticker>>,
 ) {
     for (mut transform, sticker) in sticker_query.iter_mut() {
         if let Some(planet) = sticker.planet {
-            if let Ok((planet_transform, planet)) = targets.get(planet) {
-                let center = planet_transform.translation();
-                let pos_rad = sticker.position_degrees.to_f32().to_radians();
+            if let Ok((planet_transform, planet, elevation)) = targets.get(planet) {
                 let sink = 0.75;
-                transform.translation.x = center.x + pos_rad.sin() * (planet.radius - sink);
-                transform.translation.y = center.y + pos_rad.cos() * (planet.radius - sink);
-
-                let direction = Vec2::new(pos_rad.sin(), pos_rad.cos());
-                let rotation = Quat::from_rotation_arc(
-                    Vec3::Y,
-                    Vec3::new(direction.x, direction.y, 0.0).normalize(),
-                );
+                let position_degrees = sticker.position_degrees.to_f32();
+                let ground = planet.radius + elevation.map_or(0., |elevation| elevation.height_at(position_degrees));
+                let (position, rotation) = surface_point(planet_transform, ground - sink, position_degrees);
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
                 transform.rotation = rotation;
             }
         }
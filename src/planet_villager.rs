@@ -1,17 +1,38 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+use crate::construction::ConstructionSite;
+use crate::colony_rng::{ColonyRng, ColonyRngSet};
+use crate::day_night::DayCycle;
+use crate::housing::Housing;
 use crate::looping_float::LoopingFloat;
-use crate::occupable::{Occupable, OccupableType};
+use crate::occupable::{Automator, Occupable, OccupableType, ResourceType};
+use crate::pathfinding::{CachedPath, SurfaceGraphs};
 use crate::planet::PlanetWater;
 use crate::planet_sticker::{self, PlanetSticker};
 use crate::resources::Resources;
+use crate::statistics::ResourceProduced;
+use crate::storage::{deposit, Storage};
 use crate::{spritesheet_animator, natural_resource::NaturalResource};
 use rand::Rng;
 
+// World units per second a villager covers on the way to and from work, under the main planet's gravity.
+pub const WORKER_SPEED: f32 = 26.;
+// World units short of an outdoor job that a worker stops to do it.
+pub const WORK_DISTANCE: f32 = 9.;
+const WANDER_SPEED: f32 = 12.;
+
 pub enum PlanetVillagerAnimationState {
     Idle = 0,
     Run = 1,
     Cut = 2,
     Forage = 3,
+    Mine = 4,
+}
+
+#[derive(PartialEq)]
+pub enum WalkResult {
+    Arrived,
+    Walking,
+    NoPath,
 }
 
 #[derive(Component)]
@@ -24,6 +45,27 @@ pub struct VillagerWorking {
     pub current_occupable: Entity,
     pub current_work: Entity,
     pub production_interval: f32,
+    // How long the villager has been unable to find a way to where it needs to go.
+    pub stuck_time: f32,
+}
+
+impl VillagerWorking {
+    pub fn new(occupable: Entity) -> VillagerWorking {
+        VillagerWorking {
+            current_occupable: occupable,
+            current_work: occupable,
+            production_interval: 1.0,
+            stuck_time: 0.,
+        }
+    }
+
+    pub fn track_walk(&mut self, result: &WalkResult, elapsed_seconds: f32) {
+        if *result == WalkResult::NoPath {
+            self.stuck_time += elapsed_seconds;
+        } else {
+            self.stuck_time = 0.;
+        }
+    }
 }
 
 #[derive(Component)]
@@ -32,6 +74,38 @@ pub struct VillagerWandering {
     pub wait_time: f32,
 }
 
+#[derive(Component)]
+pub struct VillagerSleeping {
+    pub house: Option<Entity>,
+}
+
+// Running from a hazard, which takes over from whatever else the villager was doing, see the hazards module.
+#[derive(Component)]
+pub struct VillagerFleeing;
+
+#[derive(Component)]
+pub struct VillagerInventory {
+    pub carried: Option<ResourceType>,
+    pub amount: u32,
+    pub capacity: u32,
+}
+
+impl VillagerInventory {
+    pub fn is_full(&self) -> bool {
+        return self.amount >= self.capacity;
+    }
+}
+
+impl Default for VillagerInventory {
+    fn default() -> VillagerInventory {
+        VillagerInventory {
+            carried: None,
+            amount: 0,
+            capacity: 5,
+        }
+    }
+}
+
 impl Default for VillagerWandering {
     fn default() -> VillagerWandering {
         VillagerWandering {
@@ -47,7 +121,7 @@ impl Plugin for PlanetVillagerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             Update,
-            (handle_working_villagers, handle_wandering_villagers),
+            (handle_bedtime, handle_working_villagers, handle_wandering_villagers.in_set(ColonyRngSet::Villagers), handle_sleeping_villagers),
         );
     }
 }
@@ -59,9 +133,14 @@ fn handle_wandering_villagers(
         &mut Sprite,
         &mut Visibility,
         &mut spritesheet_animator::SpritesheetAnimator,
-    )>,
-    water_query: Query<&PlanetSticker, (With<PlanetWater>, Without<Occupable>, Without<VillagerWorking>, Without<VillagerWandering>)>,
+        &mut VillagerInventory,
+        &mut CachedPath,
+    ), (Without<VillagerSleeping>, Without<VillagerFleeing>)>,
+    graphs: Res<SurfaceGraphs>,
+    storages_query: Query<&PlanetSticker, (With<Storage>, Without<VillagerInventory>)>,
     time: Res<Time>,
+    mut resources: ResMut<Resources>,
+    mut colony_rng: ResMut<ColonyRng>,
 ) {
     for (
         mut wandering,
@@ -69,28 +148,48 @@ fn handle_wandering_villagers(
         sprite,
         mut visibility,
         mut animator,
+        mut inventory,
+        mut cached_path,
     ) in villager_query.iter_mut()
     {
         if sticker.planet.is_none() { return; }
         *visibility = Visibility::Visible;
         animator.current_animation_index = PlanetVillagerAnimationState::Idle as u32;
+        if inventory.carried.is_some() {
+            if let Some(storage_position) = find_nearest_storage(&sticker, &storages_query, &graphs) {
+                if walk_towards(
+                    &mut animator,
+                    sticker,
+                    sprite,
+                    &graphs,
+                    &mut cached_path,
+                    time.delta_secs(),
+                    storage_position,
+                    WANDER_SPEED,
+                ) == WalkResult::Arrived {
+                    deposit(&mut inventory, &mut resources);
+                }
+                continue;
+            }
+        }
         if wandering.wait_time > 0. {
             wandering.wait_time -= time.delta_secs();
             if wandering.wait_time <= 0. {
                 wandering.current_destination =
-                    sticker.position_degrees + rand::thread_rng().gen_range(-20.0..20.0)
+                    sticker.position_degrees + colony_rng.rng.gen_range(-20.0..20.0)
             }
         } else {
             if walk_towards(
                 &mut animator,
                 sticker,
                 sprite,
-                &water_query,
+                &graphs,
+                &mut cached_path,
                 time.delta_secs(),
                 wandering.current_destination,
-                7.,
-            ) {
-                wandering.wait_time = rand::thread_rng().gen_range(0.5..2.5);
+                WANDER_SPEED,
+            ) == WalkResult::Arrived {
+                wandering.wait_time = colony_rng.rng.gen_range(0.5..2.5);
             }
         }
     }
@@ -98,83 +197,41 @@ fn handle_wandering_villagers(
 
 pub fn get_walk_dir(
     villager_sticker: &PlanetSticker,
-    water_query: &Query<&PlanetSticker, (With<PlanetWater>, Without<Occupable>, Without<VillagerWorking>, Without<VillagerWandering>)>,
+    graphs: &SurfaceGraphs,
     destination: LoopingFloat<360>
 ) -> Option<i32> {
-    let shortest = villager_sticker.position_degrees.direction(destination.to_f32());
-    let longest = -shortest;
-    if is_path_free(water_query, villager_sticker.position_degrees, destination.to_f32(), shortest) {
-        return Some(shortest);
-    }
-    if is_path_free(water_query, villager_sticker.position_degrees, destination.to_f32(), longest) {
-        return Some(longest);
-    }
-    return None;
-}
-
-fn is_path_free(
-    water_query: &Query<&PlanetSticker, (With<PlanetWater>, Without<Occupable>, Without<VillagerWorking>, Without<VillagerWandering>)>,
-    start: LoopingFloat<360>,
-    end: f32,
-    dir: i32,
-) -> bool {
-    for water in water_query.iter() {
-        if is_obstructing(water, start, end, dir) {
-            return false;
-        }
-    }
-    return true;
-}
-
-fn is_obstructing(
-    water: &PlanetSticker,
-    start: LoopingFloat<360>,
-    end: f32,
-    dir: i32,
-) -> bool {
-    let dir_bool = dir == 1;
-    let Some(water_size) = water.size_degrees else { return false; };
-    let water_start = water.position_degrees - water_size / 2.;
-    let water_end = water.position_degrees  + water_size / 2.;
-    if (start.to_f32() > water_start.to_f32() && start.to_f32() < water_end.to_f32()) || (end > water_start.to_f32() && end < water_end.to_f32()) {
-        return true;
-    }
-    let a;
-    let b;
-    if dir_bool {
-        a = start.is_in_between(water_start.to_f32(), end, dir_bool);
-        b = start.is_in_between(water_end.to_f32(), end, dir_bool);
-    } else {
-        a = LoopingFloat::<360>::new(end).is_in_between(water_start.to_f32(), start.to_f32(), true);
-        b = LoopingFloat::<360>::new(end).is_in_between(water_end.to_f32(), start.to_f32(), true);
-    }
-    if a || b {
-        return true;
-    }
-    
-    return false;
+    return graphs.find_path(villager_sticker, destination).map(|path| path.direction);
 }
 
-
-fn walk_towards(
+pub fn walk_towards(
     animator: &mut spritesheet_animator::SpritesheetAnimator,
     mut sticker: Mut<PlanetSticker>,
     mut sprite: Mut<Sprite>,
-    water_query: &Query<&PlanetSticker, (With<PlanetWater>, Without<Occupable>, Without<VillagerWorking>, Without<VillagerWandering>)>,
+    graphs: &SurfaceGraphs,
+    cached_path: &mut CachedPath,
     elapsed_seconds: f32,
     destination: LoopingFloat<360>,
     speed: f32,
-) -> bool {
+) -> WalkResult {
     let seperating = sticker.position_degrees.difference(destination.to_f32());
     if seperating.abs() < 0.1 {
-        return true;
+        return WalkResult::Arrived;
     }
-    let dir_opt = get_walk_dir(&sticker, water_query, destination);
-    let Some(dir) = dir_opt else {return false;};
-    sticker.position_degrees += dir as f32 * speed * elapsed_seconds;
+    let Some(path) = graphs.cached_path(&sticker, destination, cached_path) else { return WalkResult::NoPath; };
+    let dir = path.direction;
+    // Stop at the next waypoint so the ground's cost is picked up again on the other side of it,
+    // and so fast walkers never overshoot the destination.
+    let next_waypoint = path.waypoints.first().copied().unwrap_or(destination);
+    let remaining = sticker.position_degrees.distance(next_waypoint.to_f32());
+    let step = (graphs.degrees_per_second(&sticker, speed) * graphs.speed_factor(&sticker, dir) * elapsed_seconds).min(remaining);
+    if step >= remaining && path.waypoints.len() > 1 {
+        path.waypoints.remove(0);
+    }
+    sticker.position_degrees += dir as f32 * step;
+    cached_path.moved_to(sticker.position_degrees.to_f32());
     sprite.flip_x = dir < 0;
     animator.current_animation_index = PlanetVillagerAnimationState::Run as u32;
-    return false;
+    return WalkResult::Walking;
 }
 
 fn handle_working_villagers(
@@ -186,51 +243,93 @@ fn handle_working_villagers(
         &mut Visibility,
         &mut Sprite,
         &mut spritesheet_animator::SpritesheetAnimator,
-    )>,
-    water_query: Query<&PlanetSticker, (With<PlanetWater>, Without<Occupable>, Without<VillagerWorking>, Without<VillagerWandering>)>,
+        &mut VillagerInventory,
+        &mut CachedPath,
+    ), (Without<VillagerSleeping>, Without<VillagerFleeing>)>,
+    graphs: Res<SurfaceGraphs>,
     occupable_query: Query<(&Occupable, &PlanetSticker), Without<VillagerWorking>>,
+    storages_query: Query<&PlanetSticker, (With<Storage>, Without<VillagerInventory>)>,
+    automators_query: Query<&Automator>,
+    sites_query: Query<(), With<ConstructionSite>>,
     mut natural_resource_query: Query<&mut NaturalResource>,
+    mut ev_produced: EventWriter<ResourceProduced>,
     time: Res<Time>,
     mut resources: ResMut<Resources>
 ) {
-    for (worker_entity, mut worker, sticker, mut visibility, sprite, mut animator) in
+    for (worker_entity, mut worker, sticker, mut visibility, sprite, mut animator, mut inventory, mut cached_path) in
         villager_query.iter_mut()
     {
         if sticker.planet.is_none() { return; }
         *visibility = Visibility::Visible;
+        // Builders fetch materials and build on their own, see the construction module.
+        if sites_query.contains(worker.current_work) { continue; }
         if let Ok((occupable, occupable_sticker)) = occupable_query.get(worker.current_work) {
+            // Goods only count once they reach a storage, so haul them back when full or when the current work yields something else.
+            let work_resource = natural_resource_query.get(worker.current_work).ok().map(|natural_resource| natural_resource.produced_resource);
+            if inventory.carried.is_some() && (inventory.is_full() || work_resource != inventory.carried) {
+                animator.current_animation_index = PlanetVillagerAnimationState::Idle as u32;
+                // No reachable storage counts as being stuck, so the worker gets released like any other dead end.
+                let Some(storage_position) = find_nearest_storage(&sticker, &storages_query, &graphs) else {
+                    worker.track_walk(&WalkResult::NoPath, time.delta_secs());
+                    continue;
+                };
+                let walk = walk_towards(
+                    &mut animator,
+                    sticker,
+                    sprite,
+                    &graphs,
+                    &mut cached_path,
+                    time.delta_secs(),
+                    storage_position,
+                    WORKER_SPEED,
+                );
+                worker.track_walk(&walk, time.delta_secs());
+                if walk == WalkResult::Arrived {
+                    deposit(&mut inventory, &mut resources);
+                }
+                continue;
+            }
             let mut target = occupable_sticker.position_degrees;
             if occupable.occupable_type != OccupableType::Interior {
                 target += sticker
                     .position_degrees
                     .direction(occupable_sticker.position_degrees.to_f32())
                     as f32
-                    * -5.;
+                    * -graphs.degrees_for(&sticker, WORK_DISTANCE);
             }
-            if walk_towards(
+            let walk = walk_towards(
                 &mut animator,
                 sticker,
                 sprite,
-                &water_query,
+                &graphs,
+                &mut cached_path,
                 time.delta_secs(),
                 target,
-                15.,
-            ) {
+                WORKER_SPEED,
+            );
+            worker.track_walk(&walk, time.delta_secs());
+            if walk == WalkResult::Arrived {
                 let anim = match occupable.occupable_type {
                     OccupableType::Cutting => PlanetVillagerAnimationState::Cut,
                     OccupableType::Foraging => PlanetVillagerAnimationState::Forage,
                     OccupableType::Interior => PlanetVillagerAnimationState::Idle,
                     OccupableType::Fishing => PlanetVillagerAnimationState::Forage,
+                    OccupableType::Mining => PlanetVillagerAnimationState::Mine,
+                    OccupableType::Construction => PlanetVillagerAnimationState::Cut,
                 };
                 animator.current_animation_index = anim as u32;
                 if let Ok(mut natural_resource) = natural_resource_query.get_mut(worker.current_work) {
+                    if automators_query.get(worker.current_occupable).is_ok_and(|automator| !automator.allows(&natural_resource, occupable)) {
+                        worker.current_work = worker.current_occupable;
+                        continue;
+                    }
                     worker.production_interval -= time.delta_secs();
                     if worker.production_interval <= 0.0 {
-                        let index = natural_resource.produced_resource as i32;
-                        let current_value = resources.stored.get(&index).copied().unwrap_or(0);
                         natural_resource.amount_remaining -= 1;
-                        resources.stored.insert(index, current_value + 1 as i32);
+                        inventory.carried = Some(natural_resource.produced_resource);
+                        inventory.amount += 1;
                         worker.production_interval = 1.0;
+                        ev_produced.send(ResourceProduced { occupable_type: occupable.occupable_type, amount: 1 });
                     }
                 }
                 if occupable.occupable_type == OccupableType::Interior {
@@ -250,6 +349,108 @@ fn handle_working_villagers(
     }
 }
 
+pub fn find_nearest_storage(
+    sticker: &PlanetSticker,
+    storages_query: &Query<&PlanetSticker, (With<Storage>, Without<VillagerInventory>)>,
+    graphs: &SurfaceGraphs,
+) -> Option<LoopingFloat<360>> {
+    // Nearest by walking cost, so a storage across a swamp can lose to a farther one on dry ground.
+    let mut nearest: Option<(LoopingFloat<360>, f32)> = None;
+    for storage_sticker in storages_query.iter() {
+        if storage_sticker.planet != sticker.planet { continue; }
+        let Some(path) = graphs.find_path(sticker, storage_sticker.position_degrees) else { continue; };
+        if nearest.is_some_and(|(_, best_cost)| best_cost <= path.cost) { continue; }
+        nearest = Some((storage_sticker.position_degrees, path.cost));
+    }
+    return nearest.map(|(position, _)| position);
+}
+
+fn handle_bedtime(
+    mut commands: Commands,
+    villager_query: Query<(Entity, &PlanetSticker, Option<&VillagerSleeping>), With<PlanetVillager>>,
+    cycles_query: Query<&DayCycle>,
+    houses_query: Query<(Entity, &Housing, &PlanetSticker), Without<PlanetVillager>>,
+    graphs: Res<SurfaceGraphs>,
+) {
+    let mut occupied_beds: HashMap<Entity, u32> = HashMap::new();
+    for (_, _, sleeping) in villager_query.iter() {
+        let Some(house) = sleeping.and_then(|sleeping| sleeping.house) else { continue; };
+        *occupied_beds.entry(house).or_insert(0) += 1;
+    }
+    for (villager_entity, sticker, sleeping) in villager_query.iter() {
+        let Some(planet) = sticker.planet else { continue; };
+        let Ok(cycle) = cycles_query.get(planet) else { continue; };
+        let is_night = cycle.is_night(sticker.position_degrees.to_f32());
+        if sleeping.is_some() {
+            if !is_night {
+                commands.entity(villager_entity).remove::<VillagerSleeping>();
+            }
+            continue;
+        }
+        if !is_night { continue; }
+        let mut bed: Option<(Entity, f32)> = None;
+        for (house_entity, housing, house_sticker) in houses_query.iter() {
+            if house_sticker.planet != sticker.planet { continue; }
+            if occupied_beds.get(&house_entity).copied().unwrap_or(0) >= housing.beds { continue; }
+            let dist = sticker.position_degrees.distance(house_sticker.position_degrees.to_f32());
+            if bed.is_some_and(|(_, best)| best <= dist) { continue; }
+            if get_walk_dir(sticker, &graphs, house_sticker.position_degrees).is_none() { continue; }
+            bed = Some((house_entity, dist));
+        }
+        let house = bed.map(|(house_entity, _)| house_entity);
+        if let Some(house_entity) = house {
+            *occupied_beds.entry(house_entity).or_insert(0) += 1;
+        }
+        commands.entity(villager_entity).insert(VillagerSleeping { house });
+    }
+}
+
+fn handle_sleeping_villagers(
+    mut villager_query: Query<(
+        &VillagerSleeping,
+        &mut PlanetSticker,
+        &mut Sprite,
+        &mut Visibility,
+        &mut spritesheet_animator::SpritesheetAnimator,
+        &mut CachedPath,
+    ), (Without<PlanetWater>, Without<VillagerFleeing>)>,
+    houses_query: Query<&PlanetSticker, (With<Housing>, Without<VillagerSleeping>)>,
+    graphs: Res<SurfaceGraphs>,
+    time: Res<Time>,
+) {
+    for (sleeping, sticker, sprite, mut visibility, mut animator, mut cached_path) in villager_query.iter_mut() {
+        if sticker.planet.is_none() { continue; }
+        *visibility = Visibility::Visible;
+        animator.current_animation_index = PlanetVillagerAnimationState::Idle as u32;
+        let target = if let Some(house_sticker) = sleeping.house.and_then(|house| houses_query.get(house).ok()) {
+            house_sticker.position_degrees
+        } else {
+            let mut nearest: Option<LoopingFloat<360>> = None;
+            for house_sticker in houses_query.iter() {
+                if house_sticker.planet != sticker.planet { continue; }
+                let dist = sticker.position_degrees.distance(house_sticker.position_degrees.to_f32());
+                if nearest.is_some_and(|best| sticker.position_degrees.distance(best.to_f32()) <= dist) { continue; }
+                nearest = Some(house_sticker.position_degrees);
+            }
+            // Without a free bed, wait right next to the house rather than on top of it.
+            let Some(house_position) = nearest else { continue; };
+            house_position + house_position.direction(sticker.position_degrees.to_f32()) as f32 * 10.
+        };
+        if walk_towards(
+            &mut animator,
+            sticker,
+            sprite,
+            &graphs,
+            &mut cached_path,
+            time.delta_secs(),
+            target,
+            WORKER_SPEED,
+        ) == WalkResult::Arrived && sleeping.house.is_some() {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
 pub fn spawn_villager(commands: &mut Commands, asset_server: &Res<AssetServer>, planet: Entity, position_degrees: f32, name: String) {
     commands.spawn((
         Sprite {
@@ -263,7 +464,7 @@ pub fn spawn_villager(commands: &mut Commands, asset_server: &Res<AssetServer>,
         },
         spritesheet_animator::SpritesheetAnimator::new(
             UVec2 { x: 16, y: 16 },
-            vec![vec![0.6; 2], vec![0.2; 2], vec![0.2; 4], vec![0.2; 2]],
+            vec![vec![0.6; 2], vec![0.2; 2], vec![0.2; 4], vec![0.2; 2], vec![0.25; 3]],
         ),
         planet_sticker::PlanetSticker {
             planet: Some(planet),
@@ -274,7 +475,13 @@ pub fn spawn_villager(commands: &mut Commands, asset_server: &Res<AssetServer>,
             _name: format!("{}", name),
         },
         VillagerWandering::default(),
-        PickingBehavior::IGNORE,
+        VillagerInventory::default(),
+        CachedPath::default(),
+        // Clickable for group selection, while still letting clicks through to the building behind.
+        PickingBehavior {
+            should_block_lower: false,
+            is_hoverable: true,
+        },
         Name::new("Villager")
     ));
 }
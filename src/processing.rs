@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::{
+    occupable::{spawn_occupable, OccupableParameters, OccupableType, ResourceType},
+    planet_sticker::PlanetSticker,
+    planet_villager::{VillagerFleeing, VillagerSleeping, VillagerWorking},
+    progress_indicator::{spawn_progress_indicator, ProgressIndicator},
+    resources::Resources,
+    statistics::ResourceProduced,
+};
+
+#[derive(Clone, Copy)]
+pub struct Recipe {
+    pub inputs: &'static [(ResourceType, i32)],
+    pub outputs: &'static [(ResourceType, i32)],
+    pub duration: f32,
+}
+
+pub const PLANKS_RECIPE: Recipe = Recipe {
+    inputs: &[(ResourceType::Wood, 2)],
+    outputs: &[(ResourceType::Planks, 1)],
+    duration: 4.,
+};
+
+pub const MEALS_RECIPE: Recipe = Recipe {
+    inputs: &[(ResourceType::Food, 3)],
+    outputs: &[(ResourceType::Meals, 2)],
+    duration: 5.,
+};
+
+#[derive(Component)]
+pub struct Processor {
+    pub recipe: Recipe,
+    pub progress: f32,
+    pub running: bool,
+}
+
+pub struct ProcessingPlugin;
+
+impl Plugin for ProcessingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_processors, update_processor_indicators).chain());
+    }
+}
+
+fn handle_processors(
+    mut processors_query: Query<(Entity, &mut Processor, &PlanetSticker), Without<VillagerWorking>>,
+    workers_query: Query<(&VillagerWorking, &PlanetSticker), (Without<VillagerSleeping>, Without<VillagerFleeing>)>,
+    mut resources: ResMut<Resources>,
+    mut ev_produced: EventWriter<ResourceProduced>,
+    time: Res<Time>,
+) {
+    for (processor_entity, mut processor, processor_sticker) in processors_query.iter_mut() {
+        let mut workers_inside = 0;
+        for (worker, worker_sticker) in workers_query.iter() {
+            if worker.current_work != processor_entity { continue; }
+            if worker_sticker.position_degrees.distance(processor_sticker.position_degrees.to_f32()) > 0.5 { continue; }
+            workers_inside += 1;
+        }
+        if workers_inside == 0 { continue; }
+        if !processor.running {
+            if !resources.can_afford(processor.recipe.inputs) { continue; }
+            resources.spend(processor.recipe.inputs);
+            processor.running = true;
+            processor.progress = 0.;
+        }
+        processor.progress += time.delta_secs() * workers_inside as f32;
+        if processor.progress >= processor.recipe.duration {
+            for (resource_type, amount) in processor.recipe.outputs {
+                resources.add(*resource_type, *amount);
+                ev_produced.send(ResourceProduced { occupable_type: OccupableType::Interior, amount: *amount });
+            }
+            processor.running = false;
+            processor.progress = 0.;
+        }
+    }
+}
+
+fn update_processor_indicators(
+    mut indicators_query: Query<(&Parent, &mut ProgressIndicator, &mut Visibility)>,
+    processors_query: Query<&Processor>,
+) {
+    for (parent, mut indicator, mut visibility) in indicators_query.iter_mut() {
+        let Ok(processor) = processors_query.get(parent.get()) else { continue; };
+        indicator.progress = processor.progress / processor.recipe.duration;
+        *visibility = if processor.running { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+pub fn spawn_processor(commands: &mut Commands, occupable_parameters: OccupableParameters, recipe: Recipe) -> Entity {
+    let occupable = spawn_occupable(commands, occupable_parameters);
+    let indicator = spawn_progress_indicator(commands, 16., Vec3::new(0., 36., 0.));
+    commands.entity(occupable)
+        .insert(Processor { recipe, progress: 0., running: false })
+        .add_child(indicator);
+    return occupable;
+}
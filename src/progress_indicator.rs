@@ -0,0 +1,56 @@
+use bevy::{prelude::*, sprite::Anchor};
+
+#[derive(Component)]
+pub struct ProgressIndicator {
+    pub progress: f32,
+    pub width: f32,
+}
+
+#[derive(Component)]
+struct ProgressIndicatorFill;
+
+pub struct ProgressIndicatorPlugin;
+
+impl Plugin for ProgressIndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, handle_indicators);
+    }
+}
+
+fn handle_indicators(
+    indicators_query: Query<(&ProgressIndicator, &Children)>,
+    mut fills_query: Query<&mut Sprite, With<ProgressIndicatorFill>>,
+) {
+    for (indicator, children) in indicators_query.iter() {
+        for child in children.iter() {
+            let Ok(mut sprite) = fills_query.get_mut(*child) else { continue; };
+            sprite.custom_size = Some(Vec2::new(indicator.width * indicator.progress.clamp(0., 1.), 2.));
+        }
+    }
+}
+
+pub fn spawn_progress_indicator(commands: &mut Commands, width: f32, offset: Vec3) -> Entity {
+    let indicator = commands.spawn((
+        Sprite {
+            color: Color::BLACK,
+            custom_size: Some(Vec2::new(width + 2., 4.)),
+            ..default()
+        },
+        Transform::from_translation(offset),
+        Visibility::Hidden,
+        ProgressIndicator { progress: 0., width },
+        Name::new("ProgressIndicator"),
+    )).id();
+    let fill = commands.spawn((
+        Sprite {
+            color: Color::WHITE,
+            custom_size: Some(Vec2::new(0., 2.)),
+            anchor: Anchor::CenterLeft,
+            ..default()
+        },
+        Transform::from_xyz(-width / 2., 0., 0.1),
+        ProgressIndicatorFill,
+    )).id();
+    commands.entity(indicator).add_child(fill);
+    return indicator;
+}
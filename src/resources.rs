@@ -1,13 +1,58 @@
 use bevy::{app::{App, Plugin}, prelude::Resource, utils::HashMap};
 
+use crate::occupable::ResourceType;
+
 #[derive(Resource, Default)]
 pub struct Resources {
     pub stored: HashMap<i32, i32>,
+    // Set aside for construction sites until their builders pick it up.
+    pub reserved: HashMap<i32, i32>,
+}
+
+impl Resources {
+    pub fn amount(&self, resource_type: ResourceType) -> i32 {
+        return self.stored.get(&(resource_type as i32)).copied().unwrap_or(0);
+    }
+
+    pub fn add(&mut self, resource_type: ResourceType, amount: i32) {
+        let current_value = self.amount(resource_type);
+        self.stored.insert(resource_type as i32, current_value + amount);
+    }
+
+    // What is stored and not yet promised to a construction site.
+    pub fn available(&self, resource_type: ResourceType) -> i32 {
+        return self.amount(resource_type) - self.reserved.get(&(resource_type as i32)).copied().unwrap_or(0);
+    }
+
+    pub fn can_afford(&self, costs: &[(ResourceType, i32)]) -> bool {
+        return costs.iter().all(|(resource_type, amount)| self.available(*resource_type) >= *amount);
+    }
+
+    pub fn reserve(&mut self, costs: &[(ResourceType, i32)]) {
+        for (resource_type, amount) in costs {
+            *self.reserved.entry(*resource_type as i32).or_insert(0) += amount;
+        }
+    }
+
+    pub fn release(&mut self, resource_type: ResourceType, amount: i32) {
+        let reserved = self.reserved.entry(resource_type as i32).or_insert(0);
+        *reserved = (*reserved - amount).max(0);
+    }
+
+    pub fn spend(&mut self, costs: &[(ResourceType, i32)]) {
+        for (resource_type, amount) in costs {
+            self.add(*resource_type, -amount);
+        }
+    }
 }
+
 pub struct ResourcesPlugin;
 
 impl Plugin for ResourcesPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Resources{ stored: HashMap::new()});
+        let mut resources = Resources { stored: HashMap::new(), reserved: HashMap::new() };
+        // Enough to put up the first couple of buildings, since they now need materials delivered.
+        resources.add(ResourceType::Wood, 12);
+        app.insert_resource(resources);
     }
-}
\ No newline at end of file
+}
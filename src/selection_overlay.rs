@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use crate::{
+    occupable::{Automator, SelectedOccupable},
+    planet::Planet,
+    planet_placing::{spawn_range_arc, update_range_arc, RangeArcMaterial},
+    planet_sticker::PlanetSticker,
+    planet_villager::VillagerWorking,
+};
+
+#[derive(Component)]
+struct SelectedRangeArc;
+
+pub struct SelectionOverlayPlugin;
+
+impl Plugin for SelectionOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_selected_range_arc)
+        .add_systems(Update, (handle_selected_range_arc, draw_worker_links));
+    }
+}
+
+fn spawn_selected_range_arc(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut range_arc_materials: ResMut<Assets<RangeArcMaterial>>,
+) {
+    let range_arc = spawn_range_arc(&mut commands, &mut meshes, &mut range_arc_materials);
+    commands.entity(range_arc).insert((SelectedRangeArc, Name::new("SelectedRangeArc")));
+}
+
+fn handle_selected_range_arc(
+    mut arc_query: Query<(&mut Transform, &mut Visibility, &MeshMaterial2d<RangeArcMaterial>), With<SelectedRangeArc>>,
+    automators_query: Query<(&Automator, &PlanetSticker)>,
+    planets_query: Query<(&Planet, &GlobalTransform)>,
+    selected_occupable: Res<SelectedOccupable>,
+    mut range_arc_materials: ResMut<Assets<RangeArcMaterial>>,
+) {
+    for (mut transform, mut visibility, handle) in arc_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+        let Some((automator, sticker)) = selected_occupable.occupable.and_then(|selected| automators_query.get(selected).ok()) else { continue; };
+        let Some(planet_entity) = sticker.planet else { continue; };
+        let Ok((planet, planet_transform)) = planets_query.get(planet_entity) else { continue; };
+        let Some(material) = range_arc_materials.get_mut(handle.id()) else { continue; };
+        update_range_arc(&mut transform, &mut visibility, material, planet, planet_transform, sticker.position_degrees.to_f32(), automator.range);
+    }
+}
+
+fn draw_worker_links(
+    mut gizmos: Gizmos,
+    selected_occupable: Res<SelectedOccupable>,
+    occupables_query: Query<&GlobalTransform>,
+    workers_query: Query<(&VillagerWorking, &GlobalTransform, &Visibility)>,
+) {
+    let Some(selected) = selected_occupable.occupable else { return; };
+    let Ok(occupable_transform) = occupables_query.get(selected) else { return; };
+    // Aim at the middle of the building rather than its base.
+    let occupable_position = occupable_transform.translation().xy() + occupable_transform.up().xy() * 12.;
+    for (worker, worker_transform, visibility) in workers_query.iter() {
+        if worker.current_occupable != selected { continue; }
+        if visibility == Visibility::Hidden { continue; }
+        let worker_position = worker_transform.translation().xy() + worker_transform.up().xy() * 8.;
+        gizmos.line_2d(occupable_position, worker_position, Color::WHITE);
+        gizmos.circle_2d(Isometry2d::from_translation(worker_position), 3., Color::WHITE);
+    }
+}
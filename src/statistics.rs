@@ -0,0 +1,240 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{prelude::*, render::render_resource::{AsBindGroup, ShaderRef, ShaderType}, time::common_conditions::on_timer};
+
+use crate::{
+    occupable::{OccupableType, ResourceType},
+    planet_villager::PlanetVillager,
+    resources::Resources,
+};
+
+const SAMPLE_INTERVAL: f32 = 5.;
+const HISTORY_LENGTH: usize = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Statistic {
+    Population,
+    Stored(ResourceType),
+    Production(OccupableType),
+}
+
+const TRACKED_STATISTICS: [Statistic; 12] = [
+    Statistic::Population,
+    Statistic::Stored(ResourceType::Food),
+    Statistic::Stored(ResourceType::Wood),
+    Statistic::Stored(ResourceType::Planks),
+    Statistic::Stored(ResourceType::Meals),
+    Statistic::Stored(ResourceType::Stone),
+    Statistic::Stored(ResourceType::IronOre),
+    Statistic::Production(OccupableType::Cutting),
+    Statistic::Production(OccupableType::Foraging),
+    Statistic::Production(OccupableType::Fishing),
+    Statistic::Production(OccupableType::Mining),
+    Statistic::Production(OccupableType::Interior),
+];
+
+impl Statistic {
+    pub fn label(&self) -> String {
+        match self {
+            Statistic::Population => "Population".to_owned(),
+            Statistic::Stored(resource_type) => format!("{:?}", resource_type),
+            Statistic::Production(occupable_type) => format!("{:?} /min", occupable_type),
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct ResourceProduced {
+    pub occupable_type: OccupableType,
+    pub amount: i32,
+}
+
+#[derive(Resource)]
+pub struct ColonyStatistics {
+    pub history: Vec<(Statistic, VecDeque<f32>)>,
+    pending_production: Vec<(OccupableType, i32)>,
+}
+
+impl Default for ColonyStatistics {
+    fn default() -> ColonyStatistics {
+        ColonyStatistics {
+            history: TRACKED_STATISTICS.iter().map(|statistic| (*statistic, VecDeque::with_capacity(HISTORY_LENGTH))).collect(),
+            pending_production: vec![],
+        }
+    }
+}
+
+impl ColonyStatistics {
+    pub fn series(&self, statistic: Statistic) -> Option<&VecDeque<f32>> {
+        return self.history.iter().find(|(tracked, _)| *tracked == statistic).map(|(_, series)| series);
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct LineGraphMaterial {
+    #[uniform(0)]
+    settings: LineGraphSettings,
+}
+
+// Values are packed four to a Vec4 to keep the uniform array aligned.
+#[derive(ShaderType, Debug, Clone, Default)]
+struct LineGraphSettings {
+    values: [Vec4; HISTORY_LENGTH / 4],
+    count: u32,
+}
+
+impl UiMaterial for LineGraphMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/line_graph/shader.wgsl".into()
+    }
+}
+
+#[derive(Component)]
+struct StatisticsPanel;
+
+#[derive(Component)]
+struct StatisticGraph {
+    statistic: Statistic,
+}
+
+#[derive(Component)]
+struct StatisticLabel {
+    statistic: Statistic,
+}
+
+pub struct StatisticsPlugin;
+
+impl Plugin for StatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ColonyStatistics::default())
+            .add_event::<ResourceProduced>()
+            .add_plugins(UiMaterialPlugin::<LineGraphMaterial>::default())
+            .add_systems(Startup, spawn_statistics_panel)
+            .add_systems(Update, (count_production, sample_statistics.run_if(on_timer(Duration::from_secs_f32(SAMPLE_INTERVAL)))).chain())
+            .add_systems(Update, (toggle_statistics_panel, update_statistic_graphs));
+    }
+}
+
+fn count_production(
+    mut ev_produced: EventReader<ResourceProduced>,
+    mut statistics: ResMut<ColonyStatistics>,
+) {
+    for ev in ev_produced.read() {
+        if let Some((_, amount)) = statistics.pending_production.iter_mut().find(|(occupable_type, _)| *occupable_type == ev.occupable_type) {
+            *amount += ev.amount;
+        } else {
+            statistics.pending_production.push((ev.occupable_type, ev.amount));
+        }
+    }
+}
+
+fn sample_statistics(
+    mut statistics: ResMut<ColonyStatistics>,
+    resources: Res<Resources>,
+    villagers_query: Query<(), With<PlanetVillager>>,
+) {
+    let population = villagers_query.iter().count() as f32;
+    let pending_production = std::mem::take(&mut statistics.pending_production);
+    for (statistic, series) in statistics.history.iter_mut() {
+        let value = match statistic {
+            Statistic::Population => population,
+            Statistic::Stored(resource_type) => resources.amount(*resource_type) as f32,
+            Statistic::Production(occupable_type) => {
+                let produced = pending_production.iter().find(|(produced_type, _)| produced_type == occupable_type).map(|(_, amount)| *amount).unwrap_or(0);
+                produced as f32 * 60. / SAMPLE_INTERVAL
+            }
+        };
+        if series.len() >= HISTORY_LENGTH {
+            series.pop_front();
+        }
+        series.push_back(value);
+    }
+}
+
+fn spawn_statistics_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut graph_materials: ResMut<Assets<LineGraphMaterial>>,
+) {
+    let font = TextFont {
+        font: asset_server.load("fonts/pixel.ttf"),
+        font_size: 16.0,
+        ..default()
+    };
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(5.),
+            top: Val::Px(48.),
+            width: Val::Px(404.),
+            flex_wrap: FlexWrap::Wrap,
+            padding: UiRect::all(Val::Px(6.)),
+            column_gap: Val::Px(8.),
+            row_gap: Val::Px(4.),
+            border: UiRect::all(Val::Px(2.)),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK),
+        BorderColor(Color::WHITE),
+        Visibility::Hidden,
+        StatisticsPanel,
+        Name::new("StatisticsPanel"),
+    )).with_children(|parent| {
+        for statistic in TRACKED_STATISTICS {
+            parent.spawn(Node {
+                flex_direction: FlexDirection::Column,
+                ..default()
+            }).with_children(|cell| {
+                cell.spawn((Text::new(statistic.label()), font.clone(), StatisticLabel { statistic }));
+                cell.spawn((
+                    MaterialNode(graph_materials.add(LineGraphMaterial { settings: LineGraphSettings::default() })),
+                    Node {
+                        width: Val::Px(188.),
+                        height: Val::Px(40.),
+                        ..default()
+                    },
+                    StatisticGraph { statistic },
+                ));
+            });
+        }
+    });
+}
+
+fn toggle_statistics_panel(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut panel_query: Query<&mut Visibility, With<StatisticsPanel>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyG) { return; }
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+fn update_statistic_graphs(
+    statistics: Res<ColonyStatistics>,
+    graphs_query: Query<(&MaterialNode<LineGraphMaterial>, &StatisticGraph)>,
+    mut labels_query: Query<(&mut Text, &StatisticLabel)>,
+    mut graph_materials: ResMut<Assets<LineGraphMaterial>>,
+) {
+    if !statistics.is_changed() { return; }
+    for (handle, graph) in graphs_query.iter() {
+        let Some(series) = statistics.series(graph.statistic) else { continue; };
+        let Some(material) = graph_materials.get_mut(handle.id()) else { continue; };
+        // Scale each graph to its own peak so small economies are still readable.
+        let peak = series.iter().copied().fold(1., f32::max);
+        let mut values = [Vec4::ZERO; HISTORY_LENGTH / 4];
+        for (index, value) in series.iter().enumerate() {
+            values[index / 4][index % 4] = value / peak;
+        }
+        material.settings = LineGraphSettings { values, count: series.len() as u32 };
+    }
+    for (mut text, label) in labels_query.iter_mut() {
+        let Some(series) = statistics.series(label.statistic) else { continue; };
+        let current = series.back().copied().unwrap_or(0.);
+        let peak = series.iter().copied().fold(0., f32::max);
+        text.0 = format!("{}: {} (max {})", label.statistic.label(), current.round(), peak.round());
+    }
+}
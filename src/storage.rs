@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+
+use crate::{planet_villager::VillagerInventory, resources::Resources, structure::spawn_structure};
+
+#[derive(Component)]
+pub struct Storage;
+
+pub fn spawn_storage(commands: &mut Commands, texture: Handle<Image>, planet: Entity, position_degrees: f32) -> Entity {
+    let storage = spawn_structure(commands, texture, planet, position_degrees, "Storage");
+    commands.entity(storage).insert(Storage);
+    return storage;
+}
+
+pub fn deposit(inventory: &mut VillagerInventory, resources: &mut Resources) {
+    let Some(resource_type) = inventory.carried else { return; };
+    resources.add(resource_type, inventory.amount as i32);
+    inventory.carried = None;
+    inventory.amount = 0;
+}
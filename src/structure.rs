@@ -0,0 +1,26 @@
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::{looping_float::LoopingFloat, planet_sticker::PlanetSticker, scaling_sprite::ScalingSprite};
+
+pub fn spawn_structure(commands: &mut Commands, texture: Handle<Image>, planet: Entity, position_degrees: f32, name: &'static str) -> Entity {
+    return commands.spawn((
+        Sprite {
+            image: texture,
+            anchor: Anchor::BottomCenter,
+            ..default()
+        },
+        Transform {
+            scale: Vec3::ZERO,
+            ..default()
+        },
+        PlanetSticker {
+            planet: Some(planet),
+            position_degrees: LoopingFloat::new(position_degrees),
+            size_degrees: Some(16.),
+        },
+        ScalingSprite {
+            target_scale: Vec3::ONE,
+        },
+        Name::new(name),
+    )).id();
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::{
+    blinking_sprite::BlinkingSprite,
+    notifications::{ColonyEvent, ColonyEventKind},
+    planet_sticker::PlanetSticker,
+    planet_villager::{VillagerWandering, VillagerWorking},
+};
+
+// Seconds without any way to the work before a villager gives up on it.
+const STUCK_TIMEOUT: f32 = 3.;
+const WARNING_DURATION: f32 = 8.;
+
+#[derive(Event)]
+pub struct WorkUnreachable {
+    pub villager: Entity,
+    pub occupable: Entity,
+}
+
+#[derive(Component)]
+struct UnreachableWarning {
+    remaining: f32,
+}
+
+pub struct StuckWorkersPlugin;
+
+impl Plugin for StuckWorkersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<WorkUnreachable>()
+            .add_systems(Update, (release_stuck_workers, show_unreachable_warnings, fade_unreachable_warnings).chain());
+    }
+}
+
+fn release_stuck_workers(
+    mut commands: Commands,
+    villagers_query: Query<(Entity, &VillagerWorking)>,
+    mut ev_unreachable: EventWriter<WorkUnreachable>,
+) {
+    for (villager, worker) in villagers_query.iter() {
+        if worker.stuck_time < STUCK_TIMEOUT { continue; }
+        commands
+            .entity(villager)
+            .remove::<VillagerWorking>()
+            .insert(VillagerWandering::default());
+        ev_unreachable.send(WorkUnreachable { villager, occupable: worker.current_occupable });
+    }
+}
+
+fn show_unreachable_warnings(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut ev_unreachable: EventReader<WorkUnreachable>,
+    occupables_query: Query<(&Name, &PlanetSticker)>,
+    mut warnings_query: Query<(&Parent, &mut UnreachableWarning)>,
+    mut ev_colony: EventWriter<ColonyEvent>,
+) {
+    let mut warned: Vec<Entity> = vec![];
+    for ev in ev_unreachable.read() {
+        let Ok((occupable_name, occupable_sticker)) = occupables_query.get(ev.occupable) else { continue; };
+        warn!("Villager {} cannot reach its work at {} and stopped working", ev.villager, occupable_name);
+        // Several villagers usually give up at once, keep a single icon and notification per building.
+        if warned.contains(&ev.occupable) { continue; }
+        warned.push(ev.occupable);
+        if let Some(planet) = occupable_sticker.planet {
+            ev_colony.send(ColonyEvent {
+                kind: ColonyEventKind::WorkUnreachable,
+                planet,
+                position_degrees: occupable_sticker.position_degrees.to_f32(),
+            });
+        }
+        if let Some((_, mut warning)) = warnings_query.iter_mut().find(|(parent, _)| parent.get() == ev.occupable) {
+            warning.remaining = WARNING_DURATION;
+            continue;
+        }
+        let warning = commands.spawn((
+            Sprite::from_image(asset_server.load("ui/icons/warning.png")),
+            Transform::from_xyz(0., 44., 1.),
+            BlinkingSprite { enabled: true },
+            UnreachableWarning { remaining: WARNING_DURATION },
+            Name::new("UnreachableWarning"),
+        )).id();
+        commands.entity(ev.occupable).add_child(warning);
+    }
+}
+
+fn fade_unreachable_warnings(
+    mut commands: Commands,
+    mut warnings_query: Query<(Entity, &mut UnreachableWarning)>,
+    time: Res<Time>,
+) {
+    for (entity, mut warning) in warnings_query.iter_mut() {
+        warning.remaining -= time.delta_secs();
+        if warning.remaining <= 0. {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
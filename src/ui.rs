@@ -69,29 +69,37 @@ fn spawn_ui(
                     resource_type: ResourceType::Food,
                 },
             ));
-            parent.spawn((
-                Node {
-                        width: Val::Px(32.0),
-                        height: Val::Px(32.0),
-                        ..default()
-                    },
-                ImageNode::new(asset_server.load("ui/icons/wood.png")),
-            ));
-            parent.spawn((
-                Label,
-                Text::new(""),
-                TextFont {
-                    font: asset_server.load("fonts/pixel.ttf"),
-                    font_size: 30.0,
-                    ..default()
-                },
-                ResourceText {
-                    resource_type: ResourceType::Wood,
-                },
-            ));
+            spawn_resource_text(parent, &asset_server, "ui/icons/wood.png", ResourceType::Wood);
+            spawn_resource_text(parent, &asset_server, "ui/icons/planks.png", ResourceType::Planks);
+            spawn_resource_text(parent, &asset_server, "ui/icons/meals.png", ResourceType::Meals);
+            spawn_resource_text(parent, &asset_server, "ui/icons/stone.png", ResourceType::Stone);
+            spawn_resource_text(parent, &asset_server, "ui/icons/iron_ore.png", ResourceType::IronOre);
         });
 }
 
+fn spawn_resource_text(parent: &mut ChildBuilder, asset_server: &Res<AssetServer>, icon_path: &'static str, resource_type: ResourceType) {
+    parent.spawn((
+        Node {
+                width: Val::Px(32.0),
+                height: Val::Px(32.0),
+                ..default()
+            },
+        ImageNode::new(asset_server.load(icon_path)),
+    ));
+    parent.spawn((
+        Label,
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/pixel.ttf"),
+            font_size: 30.0,
+            ..default()
+        },
+        ResourceText {
+            resource_type,
+        },
+    ));
+}
+
 fn update_resource_texts(resources: Res<Resources>, mut texts: Query<(&mut Text, &ResourceText)>) {
     for (mut text, resource_text) in texts.iter_mut() {
         if let Some(amount) = resources.stored.get(&(resource_text.resource_type as i32)) {
@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+
+use crate::{
+    occupable::{Automator, Occupable},
+    planet_placing::{Building, GetBuildingInfo},
+    resources::Resources,
+};
+
+#[derive(Event)]
+pub struct UpgradeRequest {
+    pub building: Entity,
+}
+
+pub struct UpgradesPlugin;
+
+impl Plugin for UpgradesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<UpgradeRequest>()
+            .add_systems(Update, handle_upgrades);
+    }
+}
+
+pub fn level_name(level: u32) -> &'static str {
+    match level {
+        1 => "I",
+        2 => "II",
+        3 => "III",
+        _ => "?",
+    }
+}
+
+fn handle_upgrades(
+    mut ev_upgrade: EventReader<UpgradeRequest>,
+    mut resources: ResMut<Resources>,
+    asset_server: Res<AssetServer>,
+    mut buildings_query: Query<(&mut Building, &mut Occupable, Option<&mut Automator>, &mut Sprite, &mut Transform)>,
+) {
+    for ev in ev_upgrade.read() {
+        let Ok((mut building, mut occupable, automator, mut sprite, mut transform)) = buildings_query.get_mut(ev.building) else { continue; };
+        let Some(upgrade) = building.building_type.get_upgrade_info(building.level + 1) else { continue; };
+        if !resources.can_afford(upgrade.cost) { continue; }
+        resources.spend(upgrade.cost);
+        building.level += 1;
+        occupable.max_workers = upgrade.max_workers;
+        if let Some(mut automator) = automator {
+            automator.range = upgrade.range;
+        }
+        sprite.image = asset_server.load(upgrade.texture_path);
+        // ScalingSprite eases it back down to its normal size, which reads as a pop.
+        transform.scale = Vec3::splat(1.4);
+    }
+}
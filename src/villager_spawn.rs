@@ -1,12 +1,13 @@
 use bevy::{app::*, prelude::*};
+use rand::seq::IteratorRandom;
 
-use crate::{planet::Planets, planet_sticker::PlanetSticker, planet_villager::{spawn_villager, PlanetVillager}, resources::Resources, ResourceType};
+use crate::{colony_rng::{ColonyRng, ColonyRngSet}, housing::Housing, notifications::{ColonyEvent, ColonyEventKind}, planet::Planets, planet_sticker::PlanetSticker, planet_villager::{spawn_villager, PlanetVillager}, resources::Resources, ResourceType};
 
 pub struct VillagerSpawnPlugin;
 
 impl Plugin for VillagerSpawnPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, handle_spawn);
+        app.add_systems(Update, handle_spawn.in_set(ColonyRngSet::Spawn));
     }
 }
 
@@ -15,26 +16,33 @@ fn handle_spawn(
     asset_server: Res<AssetServer>,
     mut resources: ResMut<Resources>,
     planets: Res<Planets>,
-    villagers_query: Query<&PlanetSticker, With<PlanetVillager>>
+    villagers_query: Query<&PlanetSticker, With<PlanetVillager>>,
+    houses_query: Query<(&Housing, &PlanetSticker)>,
+    mut colony_rng: ResMut<ColonyRng>,
+    mut ev_colony: EventWriter<ColonyEvent>,
 ) {
     let index = &(ResourceType::Food as i32);
     let current_value = resources.stored.get(index).copied().unwrap_or(0);
     let cap = 10;
     if current_value >= cap {
-        resources.stored.insert(*index, current_value - cap as i32);
         if let Some(main_planet) = planets.main {
-            let mut pos = 0.;
-            for villager in villagers_query.iter() {
-                if villager.planet != planets.main { continue; }
-                pos = villager.position_degrees.to_f32();
-            }
+            let population = villagers_query.iter().filter(|villager| villager.planet == planets.main).count() as u32;
+            let beds: u32 = houses_query.iter().filter(|(_, house)| house.planet == planets.main).map(|(housing, _)| housing.beds).sum();
+            if population >= beds { return; }
+            let Some((_, house)) = houses_query.iter().filter(|(_, house)| house.planet == planets.main).choose(&mut colony_rng.rng) else { return; };
+            resources.stored.insert(*index, current_value - cap);
             spawn_villager(
                 &mut commands,
                 &asset_server,
                 main_planet,
-                pos,
+                house.position_degrees.to_f32(),
                 "spawned".to_owned(),
             );
+            ev_colony.send(ColonyEvent {
+                kind: ColonyEventKind::VillagerBorn,
+                planet: main_planet,
+                position_degrees: house.position_degrees.to_f32(),
+            });
             return;
         }
         